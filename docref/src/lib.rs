@@ -1,6 +1,3 @@
-use cucumber;
-use thirtyfour;
-
 /// Example of a struct for a world derived with the [`#[worlder]`][worlder] macro.
 ///
 /// It should not be used directly, because you will not be able to specify the versions
@@ -22,3 +19,20 @@ use thirtyfour;
     thirtyfour = thirtyfour,
 )]
 pub struct AppWorld;
+
+/// Example of a page object derived with the [`#[derive(Page)]`][page] macro.
+///
+/// [page]: https://docs.rs/cucumber-thirtyfour-worlder/latest/cucumber_thirtyfour_worlder/derive.Page.html
+#[derive(cucumber_thirtyfour_worlder::Page)]
+#[page(path = "/login", world = AppWorld, thirtyfour = thirtyfour)]
+pub struct LoginPage {
+    #[locator(css = "input[type=email]")]
+    email: thirtyfour::By,
+    #[locator(test_id = "submit")]
+    submit: thirtyfour::By,
+    #[locator(role = "alert")]
+    error: AppWorldLocator,
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,451 @@
+use crate::AppWorld;
+use std::path::Path;
+
+#[test]
+fn booleans_are_parsed() {
+    for value in ["true", "1", "yes", "TRUE", "Yes", " true ", "\tyes\n"] {
+        assert_eq!(
+            AppWorld::__parse_bool("HEADLESS", value),
+            Ok(true),
+            "{value:?} should be true"
+        );
+    }
+    for value in ["false", "0", "no", "FALSE", "No", " false ", "\tno\n"] {
+        assert_eq!(
+            AppWorld::__parse_bool("HEADLESS", value),
+            Ok(false),
+            "{value:?} should be false"
+        );
+    }
+
+    for value in ["", "maybe", "2", "y", "n", "on", "off", "t rue"] {
+        assert!(
+            AppWorld::__parse_bool("HEADLESS", value).is_err(),
+            "{value:?} should be rejected"
+        );
+    }
+    assert_eq!(
+        AppWorld::__parse_bool("HEADLESS", "maybe"),
+        Err("Invalid HEADLESS environment variable value \"maybe\". \
+            Expected one of: \"true\", \"false\", \"1\", \"0\", \"yes\" or \"no\"."
+            .to_string())
+    );
+}
+
+#[test]
+fn window_size_is_parsed() {
+    assert_eq!(
+        AppWorld::__parse_window_size("WINDOW_SIZE", "1920x1080"),
+        Ok((1920, 1080, 1.0))
+    );
+    assert_eq!(
+        AppWorld::__parse_window_size("WINDOW_SIZE", " 390x844@3 "),
+        Ok((390, 844, 3.0))
+    );
+    assert_eq!(
+        AppWorld::__parse_window_size("WINDOW_SIZE", "1280x720@1.5"),
+        Ok((1280, 720, 1.5))
+    );
+
+    for value in [
+        "",
+        "1920",
+        "1920x",
+        "1920x1080x1",
+        "0x1080",
+        "-1x1080",
+        "1920.5x1080",
+        "axb",
+        "1920x1080@",
+        "1920x1080@0",
+        "1920x1080@-2",
+        "1920x1080@inf",
+        "1920x1080@x",
+    ] {
+        assert!(
+            AppWorld::__parse_window_size("WINDOW_SIZE", value).is_err(),
+            "{value:?} should be rejected"
+        );
+    }
+}
+
+#[test]
+fn env_file_is_parsed() {
+    let variables = AppWorld::__parse_env_file(
+        ".env",
+        r#"
+# Comment
+BROWSER=chrome
+export HEADLESS = false
+HOST_URL=http://localhost:3000 # trailing comment
+DRIVER_URL="http://localhost:4444 # not a comment"
+WINDOW_SIZE='1280x720 # not a comment either'
+GREETING="Hello \"world\"\nbye"
+RAW='Hello \n'
+EMPTY=
+"#,
+    )
+    .unwrap();
+    assert_eq!(variables.len(), 8);
+    assert_eq!(variables["BROWSER"], "chrome");
+    assert_eq!(variables["HEADLESS"], "false");
+    assert_eq!(variables["HOST_URL"], "http://localhost:3000");
+    assert_eq!(
+        variables["DRIVER_URL"],
+        "http://localhost:4444 # not a comment"
+    );
+    assert_eq!(variables["WINDOW_SIZE"], "1280x720 # not a comment either");
+    assert_eq!(variables["GREETING"], "Hello \"world\"\nbye");
+    assert_eq!(variables["RAW"], "Hello \\n");
+    assert_eq!(variables["EMPTY"], "");
+
+    assert!(AppWorld::__parse_env_file(".env", "BROWSER").is_err());
+}
+
+// The only test reading the environment file, which is loaded once per process.
+#[test]
+fn env_file_has_lower_precedence_than_environment() {
+    let path = std::env::temp_dir().join(format!("worlder-{}.env", std::process::id()));
+    std::fs::write(
+        &path,
+        "WORLDER_TEST_OVERRIDDEN=file\nWORLDER_TEST_FROM_FILE=file\n",
+    )
+    .unwrap();
+    // SAFETY: no other test reads or writes these environment variables.
+    unsafe {
+        std::env::set_var("WORLDER_ENV_FILE", &path);
+        std::env::set_var("WORLDER_TEST_OVERRIDDEN", "environment");
+    }
+
+    assert_eq!(AppWorld::env_file(), Some(path.as_path()));
+    assert_eq!(
+        AppWorld::__config_value("WORLDER_TEST_OVERRIDDEN").as_deref(),
+        Some("environment")
+    );
+    assert_eq!(
+        AppWorld::__config_source("WORLDER_TEST_OVERRIDDEN"),
+        "environment variable"
+    );
+    assert_eq!(
+        AppWorld::__config_value("WORLDER_TEST_FROM_FILE").as_deref(),
+        Some("file")
+    );
+    assert_eq!(
+        AppWorld::__config_source("WORLDER_TEST_FROM_FILE"),
+        format!("environment file {}", path.display())
+    );
+    assert_eq!(AppWorld::__config_value("WORLDER_TEST_UNSET"), None);
+    assert_eq!(AppWorld::__config_source("WORLDER_TEST_UNSET"), "default");
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn absolute_urls_are_detected_by_their_scheme() {
+    for url in [
+        "http://localhost:8080",
+        "https://accounts.example.com/login?next=/",
+        "HTTP://EXAMPLE.COM",
+        "about:blank",
+        "data:text/html,<p>",
+        "git+ssh://example.com",
+    ] {
+        assert!(
+            AppWorld::__is_absolute_url(url),
+            "{url:?} should be absolute"
+        );
+    }
+    for path in [
+        "",
+        "/",
+        "/login",
+        "login",
+        "/login?next=https://app/home",
+        "/login#https://app/home",
+        "?next=https://app/home",
+        "/a:b",
+        "1http://example.com",
+        "://example.com",
+    ] {
+        assert!(
+            !AppWorld::__is_absolute_url(path),
+            "{path:?} should be relative"
+        );
+    }
+}
+
+#[test]
+fn paths_are_joined_to_the_host() {
+    let join = AppWorld::__join_path;
+    assert_eq!(join("http://localhost:8080", ""), "http://localhost:8080/");
+    assert_eq!(join("http://localhost:8080", "/"), "http://localhost:8080/");
+    assert_eq!(
+        join("http://localhost:8080", "/login"),
+        "http://localhost:8080/login"
+    );
+    assert_eq!(
+        join("http://localhost:8080/", "login"),
+        "http://localhost:8080/login"
+    );
+    assert_eq!(
+        join("http://localhost:8080/app/", "/login"),
+        "http://localhost:8080/app/login"
+    );
+    assert_eq!(
+        join("http://localhost:8080/app", "//users///42/"),
+        "http://localhost:8080/app/users/42/"
+    );
+    assert_eq!(
+        join("http://localhost:8080/?lang=en#top", "/login"),
+        "http://localhost:8080/login"
+    );
+    assert_eq!(
+        join("http://localhost:8080", "/login?next=https://app/home#form"),
+        "http://localhost:8080/login?next=https://app/home#form"
+    );
+    assert_eq!(
+        join("http://localhost:8080", "/search results?q=a b#sec tion"),
+        "http://localhost:8080/search%20results?q=a%20b#sec%20tion"
+    );
+    assert_eq!(
+        join("http://localhost:8080", "/a%20b?q=%2F"),
+        "http://localhost:8080/a%20b?q=%2F"
+    );
+    assert_eq!(
+        join("http://localhost:8080", "/100%?q=50%"),
+        "http://localhost:8080/100%25?q=50%25"
+    );
+}
+
+#[test]
+fn values_are_percent_encoded() {
+    let encode = AppWorld::__percent_encode;
+    assert_eq!(encode("AZaz09-._~", ""), "AZaz09-._~");
+    assert_eq!(encode("a b/c?d&e=f#g", ""), "a%20b%2Fc%3Fd%26e%3Df%23g");
+    assert_eq!(encode("ñ€", ""), "%C3%B1%E2%82%AC");
+    assert_eq!(encode("a/b", "/"), "a/b");
+    assert_eq!(encode("100%", ""), "100%25");
+    assert_eq!(encode("%41%zz%4", "%"), "%41%25zz%254");
+    assert_eq!(
+        AppWorld::path_for("/users/{id}/{tab}", &[("id", "a/b"), ("tab", "50%")]),
+        "/users/a%2Fb/50%25"
+    );
+}
+
+#[test]
+#[should_panic(expected = "Unclosed placeholder")]
+fn path_templates_with_unclosed_placeholders_panic() {
+    let _ = AppWorld::path_for("/users/{id", &[("id", "42")]);
+}
+
+#[test]
+#[should_panic(expected = "Missing parameter \"tab\"")]
+fn path_templates_without_parameters_panic() {
+    let _ = AppWorld::path_for("/users/{id}/{tab}", &[("id", "42")]);
+}
+
+#[test]
+fn durations_are_parsed() {
+    use std::time::Duration;
+
+    let parse = AppWorld::__parse_duration;
+    assert_eq!(
+        parse("READY_TIMEOUT", "500ms"),
+        Ok(Duration::from_millis(500))
+    );
+    assert_eq!(parse("READY_TIMEOUT", "10s"), Ok(Duration::from_secs(10)));
+    assert_eq!(parse("READY_TIMEOUT", "2m"), Ok(Duration::from_mins(2)));
+    assert_eq!(
+        parse("READY_TIMEOUT", "250"),
+        Ok(Duration::from_millis(250))
+    );
+    assert_eq!(parse("READY_TIMEOUT", " 3 s "), Ok(Duration::from_secs(3)));
+
+    for value in ["", "ms", "10h", "1.5s", "-1s", "10 seconds"] {
+        assert!(
+            parse("READY_TIMEOUT", value).is_err(),
+            "{value:?} should be rejected"
+        );
+    }
+    assert_eq!(
+        parse("READY_TIMEOUT", "10h"),
+        Err("Invalid READY_TIMEOUT environment variable value \"10h\". \
+            Expected a duration like \"500ms\", \"10s\" or \"2m\"."
+            .to_string())
+    );
+}
+
+#[test]
+fn durations_overflowing_are_rejected() {
+    let parse = AppWorld::__parse_duration;
+    assert!(parse("READY_TIMEOUT", &format!("{}m", u64::MAX / 60)).is_ok());
+    assert!(parse("READY_TIMEOUT", &format!("{}m", u64::MAX / 60 + 1)).is_err());
+    assert!(parse("READY_TIMEOUT", &format!("{}0s", u64::MAX)).is_err());
+}
+
+#[test]
+fn paths_are_matched_with_globs() {
+    let matches =
+        |pattern: &str, path: &str| AppWorld::__glob_match(pattern.as_bytes(), path.as_bytes());
+    assert!(matches("/users", "/users"));
+    assert!(!matches("/users", "/users/"));
+    assert!(matches("/users/*/edit", "/users/42/edit"));
+    assert!(matches("/users/*/edit", "/users//edit"));
+    assert!(!matches("/users/*/edit", "/users/42/43/edit"));
+    assert!(matches("/users/*", "/users/42"));
+    assert!(!matches("/users/*", "/users/42/edit"));
+    assert!(matches("/users/**", "/users/42/edit"));
+    assert!(matches("/users/**", "/users/"));
+    assert!(matches("/**/edit", "/users/42/edit"));
+    assert!(!matches("/**/edit", "/users/42/show"));
+    assert!(matches("/page-?", "/page-2"));
+    assert!(!matches("/page-?", "/page-"));
+    assert!(!matches("/page-?", "/page-10"));
+    assert!(matches("**", ""));
+    assert!(!matches("", "/"));
+}
+
+#[test]
+fn values_are_percent_decoded() {
+    let decode = AppWorld::__percent_decode;
+    assert_eq!(decode("/a%20b/%2F", false), "/a b//");
+    assert_eq!(decode("%C3%B1%e2%82%ac", false), "ñ€");
+    assert_eq!(decode("a+b", false), "a+b");
+    assert_eq!(decode("a+b%2B", true), "a b+");
+    assert_eq!(decode("100%", false), "100%");
+    assert_eq!(decode("%zz%4", false), "%zz%4");
+    assert_eq!(decode("%FF", false), "\u{FFFD}");
+}
+
+#[test]
+fn css_strings_are_escaped() {
+    let escape = AppWorld::__css_string;
+    assert_eq!(escape("submit"), r#""submit""#);
+    assert_eq!(escape(r#"say "hi""#), r#""say \"hi\"""#);
+    assert_eq!(escape(r"a\b"), r#""a\\b""#);
+    assert_eq!(escape("a\nb"), r#""a\a b""#);
+    assert_eq!(escape("a\0b"), r#""a\0 b""#);
+    assert_eq!(escape("ñ €"), r#""ñ €""#);
+}
+
+#[test]
+fn js_strings_are_escaped() {
+    let escape = AppWorld::__js_string;
+    assert_eq!(escape("submit"), r#""submit""#);
+    assert_eq!(escape(r#"say "hi""#), r#""say \"hi\"""#);
+    assert_eq!(escape(r"a\b"), r#""a\\b""#);
+    assert_eq!(escape("a\nb\r"), r#""a\nb\r""#);
+    assert_eq!(escape("a\u{2028}b\u{2029}"), r#""a\u2028b\u2029""#);
+    assert_eq!(escape("a\0b"), "\"a\0b\"");
+}
+
+#[test]
+fn contents_are_base64_encoded_and_decoded() {
+    for (content, encoded) in [
+        (&b""[..], ""),
+        (b"f", "Zg=="),
+        (b"fo", "Zm8="),
+        (b"foo", "Zm9v"),
+        (b"foobar", "Zm9vYmFy"),
+        (&[0, 0xff, 0xfe, 0x80], "AP/+gA=="),
+    ] {
+        assert_eq!(AppWorld::__base64_encode(content), encoded);
+        assert_eq!(AppWorld::__base64_decode(encoded).as_deref(), Some(content));
+    }
+    let content = (0..=255).collect::<Vec<u8>>();
+    assert_eq!(
+        AppWorld::__base64_decode(&AppWorld::__base64_encode(&content)),
+        Some(content)
+    );
+    assert_eq!(
+        AppWorld::__base64_decode("Zm9v\nYmFy").as_deref(),
+        Some(&b"foobar"[..])
+    );
+    assert_eq!(AppWorld::__base64_decode("Zm9v!"), None);
+}
+
+#[test]
+fn file_names_are_sanitized() {
+    let sanitize = AppWorld::__sanitize_file_name;
+    assert_eq!(sanitize("Log in"), "Log_in");
+    assert_eq!(
+        sanitize("Given I visit \"/users/42\""),
+        "Given_I_visit_users_42"
+    );
+    assert_eq!(sanitize("a  /\\:b"), "a_b");
+    assert_eq!(sanitize("../.hidden."), "hidden");
+    assert_eq!(sanitize("page-1.2 ñ"), "page-1.2_ñ");
+    assert_eq!(sanitize("?*"), "_");
+    assert_eq!(sanitize(""), "_");
+    assert_eq!(sanitize(&"a".repeat(150)).len(), 100);
+}
+
+#[test]
+fn outline_rows_have_their_own_artifacts_directory() {
+    let feature = cucumber::gherkin::Feature::parse(
+        "Feature: Login\n\
+        \n  Scenario: Log in\n    Given a user\n\
+        \n  Scenario Outline: Log in\n    Given a <role>\n\
+        \n    Examples:\n      | role  |\n      | admin |\n",
+        cucumber::gherkin::GherkinEnv::default(),
+    )
+    .expect("Failed to parse the feature");
+    let dir =
+        |scenario| AppWorld::__scenario_dir(Path::new("target/artifacts"), &feature.name, scenario);
+    assert_eq!(
+        dir(&feature.scenarios[0]),
+        Path::new("target/artifacts/Login/Log_in")
+    );
+    assert_eq!(
+        dir(&feature.scenarios[1]),
+        Path::new("target/artifacts/Login/Log_in-6")
+    );
+}
+
+#[test]
+fn json_strings_are_escaped() {
+    let escape = AppWorld::__json_string;
+    assert_eq!(escape("submit"), r#""submit""#);
+    assert_eq!(escape(r#"say "hi""#), r#""say \"hi\"""#);
+    assert_eq!(escape(r"a\b"), r#""a\\b""#);
+    assert_eq!(escape("a\nb\r\t"), r#""a\nb\r\t""#);
+    assert_eq!(escape("a\0b\u{7f}"), r#""a\u0000b\u007f""#);
+    assert_eq!(escape("ñ €"), r#""ñ €""#);
+}
+
+#[test]
+fn screencasts_are_animated_pngs() {
+    assert_eq!(AppWorld::__crc32(b"123456789"), 0xcbf4_3926);
+
+    let png = |data: &[u8]| {
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        for (kind, data) in [
+            (b"IHDR", &[0, 0, 0, 2, 0, 0, 0, 1, 8, 2, 0, 0, 0][..]),
+            (b"IDAT", data),
+            (b"IEND", &[]),
+        ] {
+            png.extend_from_slice(&u32::try_from(data.len()).unwrap().to_be_bytes());
+            png.extend_from_slice(kind);
+            png.extend_from_slice(data);
+            png.extend_from_slice(&AppWorld::__crc32(&[&kind[..], data].concat()).to_be_bytes());
+        }
+        png
+    };
+    let (first, second) = (png(b"first"), png(b"second"));
+    let delay = std::time::Duration::from_millis(500);
+    let animated = AppWorld::__animated_png(&[(delay, &first), (delay, &second)])
+        .expect("Failed to animate the screenshots");
+
+    let chunks = AppWorld::__png_chunks(&animated).expect("Invalid animated PNG");
+    let kinds = chunks.iter().map(|(kind, _)| kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            b"IHDR", b"acTL", b"fcTL", b"IDAT", b"fcTL", b"fdAT", b"IEND"
+        ]
+    );
+    assert_eq!(chunks[1].1, [0, 0, 0, 2, 0, 0, 0, 0]);
+    assert_eq!(chunks[3].1, b"first");
+    assert_eq!(chunks[5].1, [&[0, 0, 0, 2][..], b"second"].concat());
+    assert!(AppWorld::__animated_png(&[]).is_none());
+}
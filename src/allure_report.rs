@@ -0,0 +1,217 @@
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use quote::{format_ident, quote};
+
+pub(crate) fn build_allure_report_fns(
+    struct_name_ident: &TokenTree,
+    report_ident: &Ident,
+) -> TokenStream {
+    let scenario_ident = format_ident!("{}Scenario", report_ident);
+    let step_ident = format_ident!("{}Step", report_ident);
+
+    quote! {
+        impl #report_ident {
+            // Write the result of a finished scenario to the Allure results directory,
+            // copying its artifacts as attachments.
+            fn __save_allure_result(
+                results_dir: &std::path::Path,
+                feature: &str,
+                feature_tags: &[String],
+                scenario: &#scenario_ident,
+            ) {
+                let json = #struct_name_ident::__json_string;
+                // The browser is valid once the worlds of the scenarios have been created.
+                let browser = #struct_name_ident::__discover_browser().unwrap_or_default();
+                if let Err(err) = std::fs::create_dir_all(results_dir) {
+                    #struct_name_ident::__warn(&format!(
+                        "Failed to create the Allure results directory {}: {err}",
+                        results_dir.display(),
+                    ));
+                    return;
+                }
+
+                let mut labels = vec![
+                    ("parentSuite", browser.clone()),
+                    ("suite", feature.to_string()),
+                    ("feature", feature.to_string()),
+                    ("framework", "cucumber".to_string()),
+                    ("language", "rust".to_string()),
+                ];
+                if let Some(rule) = &scenario.rule {
+                    labels.push(("subSuite", rule.clone()));
+                }
+                labels.extend(feature_tags.iter().chain(&scenario.tags).map(|tag| ("tag", tag.clone())));
+                let labels = labels
+                    .iter()
+                    .map(|(name, value)| format!("{{\"name\": \"{name}\", \"value\": {}}}", json(value)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let parameters = scenario
+                    .parameters
+                    .iter()
+                    .map(|(name, value)| format!("{{\"name\": {}, \"value\": {}}}", json(name), json(value)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                let failed = scenario.steps.iter().find(|step| step.status == "failed");
+                // Failures outside the steps, like in hooks, are reported as broken.
+                let status = match failed {
+                    Some(step) if step.section == "before" || step.section == "after" => "broken",
+                    _ => scenario.status(),
+                };
+                let steps = scenario
+                    .steps
+                    .iter()
+                    .filter(|step| step.section == "background" || step.section == "scenario" || step.status == "failed")
+                    .map(|step| Self::__allure_step(results_dir, step))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                let full_name = format!("{browser}: {feature}: {}", scenario.name);
+                let history_id = {
+                    use std::hash::{Hash as _, Hasher as _};
+
+                    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                    (&full_name, &scenario.parameters).hash(&mut hasher);
+                    format!("{:016x}", hasher.finish())
+                };
+                let uuid = #struct_name_ident::__allure_uuid();
+                let (start, stop) = #struct_name_ident::__allure_times(scenario.started, scenario.duration);
+                let result = format!(
+                    "{{\"uuid\": \"{uuid}\", \"historyId\": \"{history_id}\", \"name\": {}, \"fullName\": {}, \
+                    \"description\": {}, \"status\": \"{status}\", \"statusDetails\": {}, \"stage\": \"finished\", \
+                    \"start\": {start}, \"stop\": {stop}, \"labels\": [{labels}], \"parameters\": [{parameters}], \
+                    \"steps\": [{steps}], \"attachments\": []}}\n",
+                    json(&scenario.name),
+                    json(&full_name),
+                    json(&scenario.description),
+                    #struct_name_ident::__allure_status_details(failed.and_then(|step| step.error.as_deref())),
+                );
+                let path = results_dir.join(format!("{uuid}-result.json"));
+                if let Err(err) = std::fs::write(&path, result) {
+                    #struct_name_ident::__warn(&format!(
+                        "Failed to save the Allure result {}: {err}",
+                        path.display(),
+                    ));
+                }
+            }
+
+            fn __allure_step(results_dir: &std::path::Path, step: &#step_ident) -> String {
+                let json = #struct_name_ident::__json_string;
+                let attachments = step
+                    .attachments
+                    .iter()
+                    .filter_map(|path| {
+                        let source = format!(
+                            "{}-attachment.{}",
+                            #struct_name_ident::__allure_uuid(),
+                            path.extension().unwrap_or_default().to_string_lossy(),
+                        );
+                        std::fs::copy(path, results_dir.join(&source))
+                            .map_err(|err| {
+                                #struct_name_ident::__warn(&format!(
+                                    "Failed to attach the artifact {}: {err}",
+                                    path.display(),
+                                ));
+                            })
+                            .ok()?;
+                        Some(format!(
+                            "{{\"name\": {}, \"source\": {}, \"type\": \"{}\"}}",
+                            json(&path.file_name().unwrap_or_default().to_string_lossy()),
+                            json(&source),
+                            #struct_name_ident::__mime_type(path),
+                        ))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let (start, stop) = #struct_name_ident::__allure_times(step.started, step.duration);
+                format!(
+                    "{{\"name\": {}, \"status\": \"{}\", \"statusDetails\": {}, \"stage\": \"finished\", \
+                    \"start\": {start}, \"stop\": {stop}, \"steps\": [], \"parameters\": [], \"attachments\": [{attachments}]}}",
+                    json(&format!("{}{}", step.keyword, step.name)),
+                    step.status,
+                    #struct_name_ident::__allure_status_details(step.error.as_deref()),
+                )
+            }
+
+            fn __save_allure_environment(results_dir: &std::path::Path) {
+                let environment = #struct_name_ident::__report_metadata()
+                    .into_iter()
+                    .map(|(name, value)| {
+                        // The browser is the one of the last run of a matrix otherwise.
+                        let value = if name == "browser" {
+                            #struct_name_ident::__discover_browsers().map_or(value, |browsers| browsers.join(","))
+                        } else {
+                            value
+                        };
+                        format!("{name}={}\n", value.replace('\\', "\\\\").replace('\n', "\\n"))
+                    })
+                    .collect::<String>();
+                let saved = std::fs::create_dir_all(results_dir)
+                    .and_then(|()| std::fs::write(results_dir.join("environment.properties"), environment));
+                match saved {
+                    Ok(()) => {
+                        #struct_name_ident::__info(&format!("Allure results saved to {}", results_dir.display()));
+                    }
+                    Err(err) => #struct_name_ident::__warn(&format!(
+                        "Failed to save the Allure environment to {}: {err}",
+                        results_dir.display(),
+                    )),
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn build_allure_report_world_fns() -> TokenStream {
+    quote! {
+        fn __allure_status_details(error: Option<&str>) -> String {
+            match error {
+                Some(error) => format!(
+                    "{{\"message\": {}, \"trace\": {}}}",
+                    Self::__json_string(error.lines().next().unwrap_or_default()),
+                    Self::__json_string(error),
+                ),
+                None => "{}".to_string(),
+            }
+        }
+
+        // Start and stop of a scenario or a step in milliseconds since the Unix epoch.
+        fn __allure_times(started: std::time::SystemTime, duration: std::time::Duration) -> (u128, u128) {
+            let start = started
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default();
+            (start.as_millis(), (start + duration).as_millis())
+        }
+
+        // Random enough UUID v4 for the names of the files of the results, without
+        // depending on a random number generator.
+        fn __allure_uuid() -> String {
+            use std::hash::{BuildHasher as _, Hash as _, Hasher as _};
+
+            static COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+            let mut halves = [0u64; 2];
+            for half in &mut halves {
+                let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+                (
+                    COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+                    std::process::id(),
+                    std::time::SystemTime::now(),
+                )
+                    .hash(&mut hasher);
+                *half = hasher.finish();
+            }
+            // Version 4 and variant 1 bits.
+            let value = (u128::from(halves[0]) << 64 | u128::from(halves[1])) & !(0xf_u128 << 76 | 0xc_u128 << 60)
+                | 0x4_u128 << 76
+                | 0x8_u128 << 60;
+            let hex = format!("{value:032x}");
+            format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+        }
+
+        fn __discover_allure_results() -> Option<std::path::PathBuf> {
+            Self::__config_value("ALLURE_RESULTS")
+                .filter(|value| !value.trim().is_empty())
+                .map(|value| std::path::PathBuf::from(value.trim()))
+        }
+    }
+}
@@ -0,0 +1,776 @@
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use quote::quote;
+
+/// Size of the whole document, which Chrome needs to capture a full page screenshot.
+const DOCUMENT_SIZE_SCRIPT: &str = "const root = document.documentElement; \
+    const body = document.body || root; \
+    return [Math.max(root.scrollWidth, body.scrollWidth), Math.max(root.scrollHeight, body.scrollHeight)];";
+
+/// Window and viewport sizes and contents of the storages of the page, which
+/// may be inaccessible, like in sandboxed iframes.
+const SNAPSHOT_SCRIPT: &str = "const entries = (storage) => { \
+        try { \
+            return Object.keys(window[storage]).map((key) => [key, window[storage].getItem(key)]); \
+        } catch (error) { \
+            return []; \
+        } \
+    }; \
+    return [ \
+        [window.outerWidth, window.outerHeight], \
+        [window.innerWidth, window.innerHeight], \
+        entries('localStorage'), \
+        entries('sessionStorage'), \
+    ];";
+
+pub(crate) fn build_writer_struct(
+    cucumber: &syn::Path,
+    vis_ident: &TokenTree,
+    struct_name_ident: &TokenTree,
+    writer_ident: &Ident,
+    report_ident: &Ident,
+) -> TokenStream {
+    quote! {
+        #[doc = "Cucumber writer that saves the artifacts of the failed steps of the world, collects"]
+        #[doc = "the report of the run and forwards all the events to the wrapped writer."]
+        #[doc = ""]
+        #[doc = "Used by the `runner` function of the world, so there's no need to use it directly."]
+        #[doc = "It dereferences to the wrapped writer, so the statistics of the run are available."]
+        #vis_ident struct #writer_ident<Wr> {
+            writer: Wr,
+            report: #report_ident,
+        }
+
+        impl<Wr> #writer_ident<Wr> {
+            #[doc = "Wrap the given writer."]
+            #[must_use]
+            pub fn new(writer: Wr) -> Self {
+                Self {
+                    writer,
+                    report: #report_ident::default(),
+                }
+            }
+
+            #[doc = "Get the wrapped writer."]
+            #[must_use]
+            pub fn inner_writer(&self) -> &Wr {
+                &self.writer
+            }
+
+            #[doc = "Get the report of the run."]
+            #[must_use]
+            pub fn report(&self) -> &#report_ident {
+                &self.report
+            }
+        }
+
+        impl<Wr> std::ops::Deref for #writer_ident<Wr> {
+            type Target = Wr;
+
+            fn deref(&self) -> &Wr {
+                &self.writer
+            }
+        }
+
+        impl<Wr: #cucumber::Writer<#struct_name_ident>> #cucumber::Writer<#struct_name_ident>
+            for #writer_ident<Wr>
+        {
+            type Cli = Wr::Cli;
+
+            async fn handle_event(
+                &mut self,
+                event: #cucumber::parser::Result<
+                    #cucumber::Event<#cucumber::event::Cucumber<#struct_name_ident>>,
+                >,
+                cli: &Self::Cli,
+            ) {
+                use #cucumber::event::{Cucumber, Feature, Hook, Rule, Scenario, Step};
+
+                let failure = match event.as_ref().map(|event| &event.value) {
+                    Ok(Cucumber::Feature(
+                        feature,
+                        Feature::Scenario(scenario, event)
+                        | Feature::Rule(_, Rule::Scenario(scenario, event)),
+                    )) => match &event.event {
+                        Scenario::Step(step, Step::Failed(_, _, Some(world), _))
+                        | Scenario::Background(step, Step::Failed(_, _, Some(world), _)) => Some((
+                            feature.name.clone(),
+                            std::sync::Arc::clone(scenario),
+                            format!("{}{}", step.keyword, step.value),
+                            std::sync::Arc::clone(world),
+                        )),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                // Save the artifacts as soon as the step fails, before the events are
+                // reordered by the normalization of the wrapped writer.
+                if let Some((feature, scenario, step, world)) = failure {
+                    world.__save_failure_artifacts(&feature, &scenario, &step).await;
+                }
+                let traced = match event.as_ref().map(|event| &event.value) {
+                    Ok(Cucumber::Feature(
+                        feature,
+                        Feature::Scenario(scenario, event)
+                        | Feature::Rule(_, Rule::Scenario(scenario, event)),
+                    )) => match &event.event {
+                        Scenario::Step(_, Step::Started)
+                        | Scenario::Background(_, Step::Started)
+                        | Scenario::Hook(_, Hook::Started) => None,
+                        Scenario::Step(..) | Scenario::Background(..) | Scenario::Hook(..) => {
+                            Some(#struct_name_ident::__scenario_dir(
+                                &#struct_name_ident::__discover_artifacts_dir(),
+                                &feature.name,
+                                scenario,
+                            ))
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                // The snapshot of a traced step is taken before it's recorded, while the
+                // page is as the step left it.
+                if let Some(dir) = traced {
+                    #struct_name_ident::__snapshot_trace_step(&dir).await;
+                }
+                let finished = matches!(event.as_ref().map(|event| &event.value), Ok(Cucumber::Finished));
+                if let Ok(event) = &event {
+                    self.report.__record(&event.value);
+                }
+                self.writer.handle_event(event, cli).await;
+                if finished {
+                    self.report.__save();
+                }
+            }
+        }
+
+        impl<Wr: #cucumber::writer::Stats<#struct_name_ident>> #cucumber::writer::Stats<#struct_name_ident>
+            for #writer_ident<Wr>
+        {
+            fn passed_steps(&self) -> usize {
+                self.writer.passed_steps()
+            }
+
+            fn skipped_steps(&self) -> usize {
+                self.writer.skipped_steps()
+            }
+
+            fn failed_steps(&self) -> usize {
+                self.writer.failed_steps()
+            }
+
+            fn retried_steps(&self) -> usize {
+                self.writer.retried_steps()
+            }
+
+            fn parsing_errors(&self) -> usize {
+                self.writer.parsing_errors()
+            }
+
+            fn hook_errors(&self) -> usize {
+                self.writer.hook_errors()
+            }
+
+            fn execution_has_failed(&self) -> bool {
+                self.writer.execution_has_failed()
+            }
+        }
+
+        impl<Wr: #cucumber::writer::Normalized> #cucumber::writer::Normalized for #writer_ident<Wr> {}
+    }
+}
+
+pub(crate) fn build_artifacts_fns(
+    cucumber: &syn::Path,
+    thirtyfour: &syn::Path,
+    struct_name_ident: &TokenTree,
+    writer_ident: &Ident,
+) -> TokenStream {
+    quote! {
+        #[doc = "Create a cucumber runner for the world that saves artifacts of the failed steps."]
+        #[doc = ""]
+        #[doc = "It's the runner of [`cucumber::World::cucumber`] with a writer that saves the"]
+        #[doc = "screenshots as `ARTIFACTS_DIR/<feature>/<scenario>/<step>.png` and the snapshots"]
+        #[doc = "of the DOM as `<step>.html` and `<step>.json` in the same directory, with the"]
+        #[doc = "names sanitized, so the artifacts are saved without any other code."]
+        #[doc = "After each scenario, the policy defined by [`console_errors`](Self::console_errors)"]
+        #[doc = "is applied and the console messages of the failed scenarios are saved as"]
+        #[doc = "`console.log` in the directory of the scenario."]
+        #[doc = ""]
+        #[doc = "When [`trace`](Self::trace) is enabled, the trace of the scenario is saved as"]
+        #[doc = "`trace.zip` in the same directory, and when [`screencast`](Self::screencast) is"]
+        #[doc = "enabled, the screencast of the scenario as `screencast.png`. The command log of the"]
+        #[doc = "scenario written when [`command_log`](Self::command_log) is `\"file\"` is attached"]
+        #[doc = "to the reports too."]
+        #[doc = ""]
+        #[doc = "```rust,ignore"]
+        #[doc = "AppWorld::runner().run_and_exit(\"tests/features\").await;"]
+        #[doc = "```"]
+        #[doc = ""]
+        #[doc = "The runner comes with `before` and `after` hooks, which are replaced by setting"]
+        #[doc = "other ones, so add the hooks to run before and after each scenario with"]
+        #[doc = "[`before_scenario_with`](Self::before_scenario_with) and"]
+        #[doc = "[`after_scenario_with`](Self::after_scenario_with) instead."]
+        #[doc = ""]
+        #[doc = "[`cucumber::World::cucumber`]: https://docs.rs/cucumber/latest/cucumber/trait.World.html#method.cucumber"]
+        #[must_use]
+        pub fn runner<I: AsRef<std::path::Path>>() -> #cucumber::Cucumber<
+            Self,
+            #cucumber::parser::Basic,
+            I,
+            #cucumber::runner::Basic<Self>,
+            #writer_ident<#cucumber::writer::Summarize<#cucumber::writer::Normalize<Self, #cucumber::writer::Basic>>>,
+        > {
+            use #cucumber::WriterExt as _;
+
+            Self::__record_commands();
+            <Self as #cucumber::World>::cucumber()
+                .with_writer(#writer_ident::new(#cucumber::writer::Basic::stdout().summarized()))
+                .before(Self::__before_scenario_hook as #cucumber::runner::basic::BeforeHookFn<Self>)
+                .after(Self::__after_scenario_hook as #cucumber::runner::basic::AfterHookFn<Self>)
+        }
+
+        #[doc = "Add a hook run before each scenario by the runner returned by [`runner`](Self::runner)."]
+        #[doc = ""]
+        #[doc = "The hooks are run in the order they're added, after the trace, the screencast and"]
+        #[doc = "the command log of the scenario are started. They take the same arguments as the"]
+        #[doc = "ones set with `cucumber::Cucumber::before`."]
+        #[doc = ""]
+        #[doc = "```rust,ignore"]
+        #[doc = "AppWorld::before_scenario_with(|_feature, _rule, _scenario, world| {"]
+        #[doc = "    Box::pin(async move {"]
+        #[doc = "        world.goto_path(\"/login\").await.unwrap();"]
+        #[doc = "    })"]
+        #[doc = "});"]
+        #[doc = "```"]
+        pub fn before_scenario_with(hook: #cucumber::runner::basic::BeforeHookFn<Self>) {
+            Self::__before_scenario_hooks()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .push(hook);
+        }
+
+        // `Self` can't be used in the type of a static, so the world is named.
+        fn __before_scenario_hooks() -> &'static std::sync::Mutex<Vec<#cucumber::runner::basic::BeforeHookFn<Self>>> {
+            static BEFORE_SCENARIO_HOOKS: std::sync::Mutex<
+                Vec<#cucumber::runner::basic::BeforeHookFn<#struct_name_ident>>,
+            > = std::sync::Mutex::new(vec![]);
+            &BEFORE_SCENARIO_HOOKS
+        }
+
+        fn __before_scenario_hook<'a>(
+            feature: &'a #cucumber::gherkin::Feature,
+            rule: Option<&'a #cucumber::gherkin::Rule>,
+            scenario: &'a #cucumber::gherkin::Scenario,
+            world: &'a mut Self,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + 'a>> {
+            Box::pin(async move {
+                world.__start_trace(&world.__scenario_artifacts_dir(&feature.name, scenario));
+                world.__start_screencast();
+                world.__start_command_log(&feature.name, scenario);
+                let hooks = Self::__before_scenario_hooks()
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .clone();
+                for hook in hooks {
+                    hook(feature, rule, scenario, world).await;
+                }
+            })
+        }
+
+        #[doc = "Add a hook run after each scenario by the runner returned by [`runner`](Self::runner)."]
+        #[doc = ""]
+        #[doc = "The hooks are run in the order they're added, after the screencast is saved and the"]
+        #[doc = "console policy is applied, and before the scenario is failed by it. They take the"]
+        #[doc = "same arguments as the ones set with `cucumber::Cucumber::after`."]
+        #[doc = ""]
+        #[doc = "```rust,ignore"]
+        #[doc = "AppWorld::after_scenario_with(|_feature, _rule, _scenario, _finished, world| {"]
+        #[doc = "    Box::pin(async move {"]
+        #[doc = "        if let Some(world) = world {"]
+        #[doc = "            world.goto_path(\"/logout\").await.unwrap();"]
+        #[doc = "        }"]
+        #[doc = "    })"]
+        #[doc = "});"]
+        #[doc = "```"]
+        pub fn after_scenario_with(hook: #cucumber::runner::basic::AfterHookFn<Self>) {
+            Self::__after_scenario_hooks()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .push(hook);
+        }
+
+        // `Self` can't be used in the type of a static, so the world is named.
+        fn __after_scenario_hooks() -> &'static std::sync::Mutex<Vec<#cucumber::runner::basic::AfterHookFn<Self>>> {
+            static AFTER_SCENARIO_HOOKS: std::sync::Mutex<
+                Vec<#cucumber::runner::basic::AfterHookFn<#struct_name_ident>>,
+            > = std::sync::Mutex::new(vec![]);
+            &AFTER_SCENARIO_HOOKS
+        }
+
+        fn __after_scenario_hook<'a>(
+            feature: &'a #cucumber::gherkin::Feature,
+            rule: Option<&'a #cucumber::gherkin::Rule>,
+            scenario: &'a #cucumber::gherkin::Scenario,
+            finished: &'a #cucumber::event::ScenarioFinished,
+            mut world: Option<&'a mut Self>,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + 'a>> {
+            Box::pin(async move {
+                let mut console_report = None;
+                if let Some(world) = world.as_deref_mut() {
+                    let failed = matches!(finished, #cucumber::event::ScenarioFinished::StepFailed(..));
+                    world.__stop_screencast(&feature.name, scenario, failed).await;
+                    let dir = world.__scenario_artifacts_dir(&feature.name, scenario);
+                    console_report = world.__check_console(&dir, &scenario.name, failed).await;
+                }
+                let hooks = Self::__after_scenario_hooks()
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .clone();
+                for hook in hooks {
+                    hook(feature, rule, scenario, finished, world.as_deref_mut()).await;
+                }
+                if let Some(report) = console_report {
+                    panic!("{report}");
+                }
+            })
+        }
+
+        #[doc = "Get the directory where the artifacts of the failed steps are saved."]
+        #[doc = ""]
+        #[doc = "It's defined by the `ARTIFACTS_DIR` environment variable, which defaults to"]
+        #[doc = "`target/artifacts`. When running a browser matrix, the name of the browser is"]
+        #[doc = "appended to it."]
+        #[must_use]
+        pub fn artifacts_dir(&self) -> &std::path::Path {
+            &self.artifacts_dir
+        }
+
+        #[doc = "Get the screenshots taken when a step fails."]
+        #[doc = ""]
+        #[doc = "It's defined by the `SCREENSHOT_ON_FAILURE` environment variable, one of `\"full\"`"]
+        #[doc = "(the whole page, default), `\"viewport\"`, `\"both\"` or `\"off\"`."]
+        #[must_use]
+        pub fn screenshot_on_failure(&self) -> &str {
+            &self.screenshot_on_failure
+        }
+
+        #[doc = "Get if a snapshot of the DOM is saved when a step fails."]
+        #[doc = ""]
+        #[doc = "It's defined by the `SNAPSHOT_ON_FAILURE` environment variable, which defaults to `true`."]
+        #[doc = "The snapshot consists of the page source, saved as `<step>.html`, and the URL, the"]
+        #[doc = "window size, the cookies and the contents of the storages of the page, saved as"]
+        #[doc = "`<step>.json`."]
+        #[must_use]
+        pub fn snapshot_on_failure(&self) -> bool {
+            self.snapshot_on_failure
+        }
+
+        #[doc = "Get the maximum size in bytes of each file of the snapshots of the DOM."]
+        #[doc = ""]
+        #[doc = "It's defined by the `SNAPSHOT_MAX_SIZE` environment variable, like `500KB` or"]
+        #[doc = "`2MB`, which defaults to `5MB`. The page source is truncated and the entries of"]
+        #[doc = "the metadata that don't fit are omitted, marking the metadata as `truncated`."]
+        #[must_use]
+        pub fn snapshot_max_size(&self) -> usize {
+            self.snapshot_max_size
+        }
+
+        #[doc = "Add a hook to redact the values saved in the snapshots of the DOM."]
+        #[doc = ""]
+        #[doc = "The hook receives the name of the field and its value, and returns the value to"]
+        #[doc = "save. The fields are `page_source`, `url`, `cookie:<name>`, `localStorage:<key>`"]
+        #[doc = "and `sessionStorage:<key>`, and `command` for the payloads of the WebDriver commands"]
+        #[doc = "recorded in traces. Hooks are applied in the order they're added. Screenshots are"]
+        #[doc = "not redacted."]
+        #[doc = ""]
+        #[doc = "```rust,ignore"]
+        #[doc = "AppWorld::redact_snapshots_with(|field, value| {"]
+        #[doc = "    if field == \"cookie:session\" {"]
+        #[doc = "        \"[REDACTED]\".to_string()"]
+        #[doc = "    } else {"]
+        #[doc = "        value.to_string()"]
+        #[doc = "    }"]
+        #[doc = "});"]
+        #[doc = "```"]
+        pub fn redact_snapshots_with(hook: impl Fn(&str, &str) -> String + Send + 'static) {
+            Self::__redactions()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .push(Box::new(hook));
+        }
+
+        // Uses the full page screenshot command of geckodriver for Firefox and the
+        // Chrome DevTools Protocol for Chrome and Edge.
+        async fn __full_page_screenshot(&self) -> Result<Vec<u8>, #thirtyfour::error::WebDriverError> {
+            let handle = std::sync::Arc::clone(&self.driver().handle);
+            if self.browser == "firefox" {
+                return #thirtyfour::extensions::addons::firefox::FirefoxTools::new(handle)
+                    .full_screenshot_as_png()
+                    .await;
+            }
+            let (width, height) = self
+                .driver()
+                .execute(#DOCUMENT_SIZE_SCRIPT, vec![])
+                .await?
+                .convert::<(u64, u64)>()?;
+            let mut clip = #thirtyfour::Capabilities::new();
+            for (name, value) in [("x", 0), ("y", 0), ("width", width), ("height", height), ("scale", 1)] {
+                clip.insert(name.to_string(), value.into());
+            }
+            let mut params = #thirtyfour::Capabilities::new();
+            params.insert("format".to_string(), "png".into());
+            params.insert("captureBeyondViewport".to_string(), true.into());
+            params.insert("clip".to_string(), clip.into());
+            let screenshot = if self.browser == "edge" {
+                // The DevTools of thirtyfour only know the endpoint of Chrome.
+                let mut command = #thirtyfour::Capabilities::new();
+                command.insert("cmd".to_string(), "Page.captureScreenshot".into());
+                command.insert("params".to_string(), params.into());
+                self.__post_session_command("ms/cdp/execute", command)
+                    .await?
+                    .value_json()?
+            } else {
+                #thirtyfour::extensions::cdp::ChromeDevTools::new(handle)
+                    .execute_cdp_with_params("Page.captureScreenshot", params.into())
+                    .await?
+            };
+            screenshot["data"]
+                .as_str()
+                .and_then(Self::__base64_decode)
+                .ok_or_else(|| {
+                    #thirtyfour::error::WebDriverError::Json(format!(
+                        "unexpected screenshot from the driver: {screenshot}"
+                    ))
+                })
+        }
+
+        // Send a POST command with a JSON body to an endpoint of the session, for the
+        // vendor commands that are not exposed by thirtyfour.
+        async fn __post_session_command(
+            &self,
+            endpoint: &str,
+            body: #thirtyfour::Capabilities,
+        ) -> Result<#thirtyfour::session::http::CmdResponse, #thirtyfour::error::WebDriverError> {
+            #[derive(Debug)]
+            struct SessionCommand {
+                endpoint: String,
+                body: #thirtyfour::Capabilities,
+            }
+
+            impl #thirtyfour::common::command::FormatRequestData for SessionCommand {
+                fn format_request(&self, session_id: &#thirtyfour::SessionId) -> #thirtyfour::RequestData {
+                    // The method type is not exported by thirtyfour, so it's parsed into
+                    // the one expected by the request, which always accepts POST.
+                    #thirtyfour::RequestData::new(
+                        "POST".parse().unwrap_or_default(),
+                        format!("/session/{session_id}/{}", self.endpoint),
+                    )
+                    .add_body(self.body.clone().into())
+                }
+            }
+
+            self.driver()
+                .handle
+                .cmd(SessionCommand {
+                    endpoint: endpoint.to_string(),
+                    body,
+                })
+                .await
+        }
+
+        async fn __save_failure_artifacts(
+            &self,
+            feature: &str,
+            scenario: &#cucumber::gherkin::Scenario,
+            step: &str,
+        ) {
+            let dir = self.__scenario_artifacts_dir(feature, scenario);
+            let step = Self::__sanitize_file_name(step);
+            if let Err(err) = std::fs::create_dir_all(&dir) {
+                Self::__warn(&format!(
+                    "Failed to create the artifacts directory {}: {err}",
+                    dir.display(),
+                ));
+                return;
+            }
+
+            let screenshot_on_failure = self.screenshot_on_failure.as_str();
+            if matches!(screenshot_on_failure, "full" | "both") {
+                let path = dir.join(format!("{step}.png"));
+                match self.__full_page_screenshot().await {
+                    Ok(png) => Self::__write_artifact(&path, &png),
+                    Err(err) => {
+                        Self::__warn(&format!(
+                            "Failed to take a full page screenshot ({err}), taking the viewport instead"
+                        ));
+                        self.__save_viewport_screenshot(&path).await;
+                    }
+                }
+            }
+            if screenshot_on_failure == "both" {
+                self.__save_viewport_screenshot(&dir.join(format!("{step}.viewport.png"))).await;
+            } else if screenshot_on_failure == "viewport" {
+                self.__save_viewport_screenshot(&dir.join(format!("{step}.png"))).await;
+            }
+
+            if self.snapshot_on_failure {
+                let metadata = [("feature", feature), ("scenario", scenario.name.as_str()), ("step", step.as_str())];
+                self.__save_snapshot(&dir, &step, &metadata).await;
+            }
+        }
+
+        async fn __save_snapshot(&self, dir: &std::path::Path, step: &str, metadata: &[(&str, &str)]) {
+            use std::fmt::Write as _;
+
+            let source = match self.driver().source().await {
+                Ok(source) => source,
+                Err(err) => {
+                    Self::__warn(&format!("Failed to get the page source: {err}"));
+                    return;
+                }
+            };
+            let url = match self.driver().current_url().await {
+                Ok(url) => Self::__redact("url", &url.to_string()),
+                Err(err) => {
+                    Self::__warn(&format!("Failed to get the current URL: {err}"));
+                    String::new()
+                }
+            };
+            let cookies = self.driver().get_all_cookies().await.unwrap_or_else(|err| {
+                Self::__warn(&format!("Failed to get the cookies: {err}"));
+                vec![]
+            });
+            let (window_size, viewport_size, local_storage, session_storage) = self
+                .driver()
+                .execute(#SNAPSHOT_SCRIPT, vec![])
+                .await
+                .and_then(|ret| {
+                    ret.convert::<(
+                        (u64, u64),
+                        (u64, u64),
+                        Vec<(String, String)>,
+                        Vec<(String, String)>,
+                    )>()
+                })
+                .unwrap_or_else(|err| {
+                    Self::__warn(&format!("Failed to get the window size and the storages: {err}"));
+                    Default::default()
+                });
+
+            let mut truncated = false;
+            let mut html = Self::__redact("page_source", &source);
+            if html.len() > self.snapshot_max_size {
+                let mut end = self.snapshot_max_size;
+                while !html.is_char_boundary(end) {
+                    end -= 1;
+                }
+                let omitted = html.len() - end;
+                html.truncate(end);
+                _ = write!(html, "\n<!-- truncated {omitted} bytes -->\n");
+                truncated = true;
+            }
+            Self::__write_artifact(&dir.join(format!("{step}.html")), html.as_bytes());
+
+            let mut json = String::from("{\n");
+            for (name, value) in metadata {
+                _ = writeln!(json, "  \"{name}\": {},", Self::__json_string(value));
+            }
+            _ = writeln!(json, "  \"url\": {},", Self::__json_string(&url));
+            for (name, (width, height)) in [("window_size", window_size), ("viewport_size", viewport_size)] {
+                _ = writeln!(json, "  \"{name}\": {{ \"width\": {width}, \"height\": {height} }},");
+            }
+            // Entries are added while they fit in the maximum size, keeping the JSON valid.
+            let mut budget = self.snapshot_max_size.saturating_sub(json.len());
+            let mut fits = |entry: &str| {
+                let fits = entry.len() <= budget;
+                if fits {
+                    budget -= entry.len();
+                } else {
+                    truncated = true;
+                }
+                fits
+            };
+            let cookies = cookies
+                .iter()
+                .map(|cookie| {
+                    format!(
+                        "{{ \"name\": {}, \"value\": {} }}",
+                        Self::__json_string(&cookie.name),
+                        Self::__json_string(&Self::__redact(
+                            &format!("cookie:{}", cookie.name),
+                            &cookie.value,
+                        )),
+                    )
+                })
+                .filter(|entry| fits(entry))
+                .collect::<Vec<_>>();
+            let mut storages = vec![];
+            for (name, storage) in [("localStorage", &local_storage), ("sessionStorage", &session_storage)] {
+                let entries = storage
+                    .iter()
+                    .map(|(key, value)| {
+                        format!(
+                            "{}: {}",
+                            Self::__json_string(key),
+                            Self::__json_string(&Self::__redact(&format!("{name}:{key}"), value)),
+                        )
+                    })
+                    .filter(|entry| fits(entry))
+                    .collect::<Vec<_>>();
+                storages.push(entries);
+            }
+            _ = writeln!(json, "  \"cookies\": [{}],", cookies.join(", "));
+            _ = writeln!(json, "  \"local_storage\": {{ {} }},", storages[0].join(", "));
+            _ = writeln!(json, "  \"session_storage\": {{ {} }},", storages[1].join(", "));
+            _ = writeln!(json, "  \"truncated\": {truncated}\n}}");
+            Self::__write_artifact(&dir.join(format!("{step}.json")), json.as_bytes());
+        }
+
+        fn __redactions() -> &'static std::sync::Mutex<Vec<Box<dyn Fn(&str, &str) -> String + Send>>> {
+            static REDACTIONS: std::sync::Mutex<Vec<Box<dyn Fn(&str, &str) -> String + Send>>> =
+                std::sync::Mutex::new(vec![]);
+            &REDACTIONS
+        }
+
+        fn __redact(field: &str, value: &str) -> String {
+            Self::__redactions()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .iter()
+                .fold(value.to_string(), |value, hook| hook(field, &value))
+        }
+
+        fn __scenario_artifacts_dir(
+            &self,
+            feature: &str,
+            scenario: &#cucumber::gherkin::Scenario,
+        ) -> std::path::PathBuf {
+            Self::__scenario_dir(&self.artifacts_dir, feature, scenario)
+        }
+
+        // The rows of an outline are told apart by their line, as they share their name.
+        fn __scenario_dir(
+            artifacts_dir: &std::path::Path,
+            feature: &str,
+            scenario: &#cucumber::gherkin::Scenario,
+        ) -> std::path::PathBuf {
+            let mut name = Self::__sanitize_file_name(&scenario.name);
+            if !scenario.examples.is_empty() {
+                name = format!("{name}-{}", scenario.position.line);
+            }
+            artifacts_dir.join(Self::__sanitize_file_name(feature)).join(name)
+        }
+
+        async fn __save_viewport_screenshot(&self, path: &std::path::Path) {
+            match self.driver().screenshot_as_png().await {
+                Ok(png) => Self::__write_artifact(path, &png),
+                Err(err) => Self::__warn(&format!("Failed to take a screenshot of the viewport: {err}")),
+            }
+        }
+
+        fn __write_artifact(path: &std::path::Path, content: &[u8]) {
+            match std::fs::write(path, content) {
+                Ok(()) => {
+                    Self::__info(&format!("Artifact saved to {}", path.display()));
+                    Self::__saved_artifacts()
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner)
+                        .push(path.to_path_buf());
+                }
+                Err(err) => Self::__warn(&format!("Failed to save the artifact {}: {err}", path.display())),
+            }
+        }
+
+        // Artifacts saved but not yet attached to the report of the run.
+        fn __saved_artifacts() -> &'static std::sync::Mutex<Vec<std::path::PathBuf>> {
+            static SAVED_ARTIFACTS: std::sync::Mutex<Vec<std::path::PathBuf>> = std::sync::Mutex::new(vec![]);
+            &SAVED_ARTIFACTS
+        }
+
+        fn __take_artifacts(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+            let mut saved = Self::__saved_artifacts()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            let (taken, kept) = std::mem::take(&mut *saved)
+                .into_iter()
+                .partition(|path| path.parent() == Some(dir));
+            *saved = kept;
+            taken
+        }
+
+        fn __sanitize_file_name(name: &str) -> String {
+            let mut sanitized = String::with_capacity(name.len());
+            for c in name.chars() {
+                if c.is_alphanumeric() || c == '-' || c == '.' {
+                    sanitized.push(c);
+                } else if !sanitized.ends_with('_') {
+                    sanitized.push('_');
+                }
+            }
+            let sanitized = sanitized
+                .trim_matches(['_', '.'])
+                .chars()
+                .take(100)
+                .collect::<String>();
+            if sanitized.is_empty() {
+                "_".to_string()
+            } else {
+                sanitized
+            }
+        }
+
+        fn __discover_artifacts_dir() -> std::path::PathBuf {
+            let dir = std::path::PathBuf::from(
+                Self::__config_value("ARTIFACTS_DIR").unwrap_or("target/artifacts".to_string()),
+            );
+            match Self::__matrix_browser()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .as_deref()
+            {
+                Some(browser) => dir.join(browser),
+                None => dir,
+            }
+        }
+
+        fn __discover_snapshot_on_failure() -> Result<bool, String> {
+            Self::__config_value("SNAPSHOT_ON_FAILURE")
+                .map_or(Ok(true), |value| Self::__parse_bool("SNAPSHOT_ON_FAILURE", &value))
+        }
+
+        fn __discover_snapshot_max_size() -> Result<usize, String> {
+            let Some(value) = Self::__config_value("SNAPSHOT_MAX_SIZE") else {
+                return Ok(5 * 1024 * 1024);
+            };
+            let trimmed = value.trim();
+            let (number, unit) = trimmed
+                .find(|c: char| !c.is_ascii_digit())
+                .map_or((trimmed, ""), |index| trimmed.split_at(index));
+            let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+                "" | "B" => Some(1),
+                "KB" => Some(1024),
+                "MB" => Some(1024 * 1024),
+                _ => None,
+            };
+            match (number.parse::<usize>(), multiplier) {
+                (Ok(number), Some(multiplier)) if number > 0 => Ok(number.saturating_mul(multiplier)),
+                _ => Err(format!(
+                    "Invalid SNAPSHOT_MAX_SIZE environment variable value \"{value}\". \
+                    Expected a size like \"500KB\" or \"2MB\"."
+                )),
+            }
+        }
+
+        fn __discover_screenshot_on_failure() -> Result<String, String> {
+            let screenshot = Self::__config_value("SCREENSHOT_ON_FAILURE")
+                .map_or("full".to_string(), |value| value.trim().to_ascii_lowercase());
+            if !matches!(screenshot.as_str(), "full" | "viewport" | "both" | "off") {
+                return Err(format!(
+                    "Invalid SCREENSHOT_ON_FAILURE environment variable value \"{screenshot}\". \
+                    Expected one of: \"full\", \"viewport\", \"both\" or \"off\"."
+                ));
+            }
+            Ok(screenshot)
+        }
+    }
+}
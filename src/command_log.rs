@@ -0,0 +1,221 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+
+pub(crate) fn build_command_log_fns(
+    cucumber: &syn::Path,
+    tracing: Option<&syn::Path>,
+    command_ident: &Ident,
+    report_ident: &Ident,
+) -> TokenStream {
+    let step_ident = format_ident!("{}Step", report_ident);
+    let log_fns = build_log_fns(cucumber, tracing, command_ident, &step_ident);
+    let requires_tracing = if tracing.is_some() {
+        quote! {}
+    } else {
+        quote! {
+            if command_log != "off" {
+                return Err(
+                    "The COMMAND_LOG environment variable requires the `tracing` argument of the \
+                    #[worlder] macro."
+                        .to_string(),
+                );
+            }
+        }
+    };
+
+    quote! {
+        #[doc = "Get if the `WebDriver` commands sent by the worlds are logged."]
+        #[doc = ""]
+        #[doc = "It's defined by the `COMMAND_LOG` environment variable, one of `\"off\"` (default),"]
+        #[doc = "`\"on\"` or `\"file\"`, and requires the `tracing` argument of the"]
+        #[doc = "[`#[worlder]`](https://docs.rs/cucumber-thirtyfour-worlder/latest/cucumber_thirtyfour_worlder/attr.worlder.html)"]
+        #[doc = "macro. When enabled, the commands sent by `thirtyfour` in the runner of the world"]
+        #[doc = "are logged from its events with their method, endpoint, payload, duration and"]
+        #[doc = "outcome, inside spans of their scenario and step. With `\"file\"`, the commands of"]
+        #[doc = "each scenario are also written as JSON lines to"]
+        #[doc = "`ARTIFACTS_DIR/<feature>/<scenario>/commands.jsonl`."]
+        #[must_use]
+        pub fn command_log(&self) -> &str {
+            &self.command_log
+        }
+
+        #log_fns
+
+        fn __discover_command_log() -> Result<String, String> {
+            let command_log = Self::__config_value("COMMAND_LOG")
+                .map_or("off".to_string(), |value| value.trim().to_ascii_lowercase());
+            if !matches!(command_log.as_str(), "off" | "on" | "file") {
+                return Err(format!(
+                    "Invalid COMMAND_LOG environment variable value \"{command_log}\". \
+                    Expected one of: \"off\", \"on\" or \"file\"."
+                ));
+            }
+            #requires_tracing
+            Ok(command_log)
+        }
+    }
+}
+
+/// Logging of the commands recorded from the events of `thirtyfour`, which
+/// requires the `tracing` crate to be passed to the macro.
+fn build_log_fns(
+    cucumber: &syn::Path,
+    tracing: Option<&syn::Path>,
+    command_ident: &Ident,
+    step_ident: &Ident,
+) -> TokenStream {
+    let Some(tracing) = tracing else {
+        return quote! {
+            fn __start_command_log(&self, _feature: &str, _scenario: &#cucumber::gherkin::Scenario) {}
+
+            fn __command_log_step(_dir: &std::path::Path, _step: &#step_ident) {}
+
+            fn __finish_command_log(_dir: &std::path::Path) {}
+        };
+    };
+
+    quote! {
+        // Artifacts directory, feature, scenario and current step of the running scenarios
+        // by their session, with the spans of the scenario and of the step.
+        fn __command_log_scenarios() -> &'static std::sync::Mutex<
+            std::collections::BTreeMap<
+                String,
+                (std::path::PathBuf, String, String, String, #tracing::Span, #tracing::Span),
+            >,
+        > {
+            static COMMAND_LOG_SCENARIOS: std::sync::Mutex<
+                std::collections::BTreeMap<
+                    String,
+                    (std::path::PathBuf, String, String, String, #tracing::Span, #tracing::Span),
+                >,
+            > = std::sync::Mutex::new(std::collections::BTreeMap::new());
+            &COMMAND_LOG_SCENARIOS
+        }
+
+        fn __start_command_log(&self, feature: &str, scenario: &#cucumber::gherkin::Scenario) {
+            if self.command_log == "off" {
+                return;
+            }
+            let dir = self.__scenario_artifacts_dir(feature, scenario);
+            // The log of a previous run or attempt of the scenario is replaced.
+            if self.command_log == "file" {
+                _ = std::fs::remove_file(dir.join("commands.jsonl"));
+            }
+            let span = #tracing::info_span!("scenario", feature = %feature, scenario = %scenario.name);
+            Self::__command_log_scenarios()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .insert(
+                    self.driver().session_id().to_string(),
+                    (dir, feature.to_string(), scenario.name.clone(), String::new(), span.clone(), span),
+                );
+        }
+
+        // Set the step of the commands logged for a scenario when the step or the hook starts.
+        fn __command_log_step(dir: &std::path::Path, step: &#step_ident) {
+            let mut scenarios = Self::__command_log_scenarios()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            let scenario = scenarios.values_mut().find(|(scenario_dir, ..)| scenario_dir == dir);
+            if let Some((_, _, _, current, scenario_span, step_span)) = scenario {
+                *current = format!("{}{}", step.keyword, step.name);
+                *step_span = #tracing::info_span!(parent: &*scenario_span, "step", step = %current);
+            }
+        }
+
+        // Stop logging the commands of a finished scenario, attaching its log.
+        fn __finish_command_log(dir: &std::path::Path) {
+            Self::__command_log_scenarios()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .retain(|_, (scenario_dir, ..)| scenario_dir != dir);
+            let path = dir.join("commands.jsonl");
+            if path.is_file() {
+                Self::__info(&format!("Artifact saved to {}", path.display()));
+                Self::__saved_artifacts()
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .push(path);
+            }
+        }
+
+        // Log a command when it's sent and when the driver responds, so hanging commands
+        // can be told apart. Called by the recorder of the commands.
+        fn __log_command(command: &#command_ident, started: bool, file: bool) {
+            let scenario = Self::__command_log_scenarios()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .get(&command.session_id)
+                .cloned();
+            let span = scenario.as_ref().map_or_else(#tracing::Span::none, |(.., span)| span.clone());
+            let duration_ms = command.duration.as_secs_f64() * 1000.0;
+            if started {
+                #tracing::debug!(
+                    parent: &span,
+                    method = %command.method,
+                    endpoint = %command.endpoint,
+                    payload = %command.payload,
+                    "WebDriver command started",
+                );
+            } else if let Some(error) = &command.error {
+                #tracing::warn!(
+                    parent: &span,
+                    method = %command.method,
+                    endpoint = %command.endpoint,
+                    payload = %command.payload,
+                    duration_ms,
+                    status = command.status,
+                    error = %error,
+                    "WebDriver command failed",
+                );
+            } else {
+                #tracing::info!(
+                    parent: &span,
+                    method = %command.method,
+                    endpoint = %command.endpoint,
+                    payload = %command.payload,
+                    duration_ms,
+                    status = command.status,
+                    "WebDriver command finished",
+                );
+            }
+
+            let (true, Some((dir, feature, scenario, step, ..))) = (file, scenario) else {
+                return;
+            };
+            let json = Self::__json_string;
+            let line = format!(
+                "{{\"event\": \"{}\", \"time\": {}, \"feature\": {}, \"scenario\": {}, \"step\": {}, \
+                \"method\": {}, \"endpoint\": {}, \"payload\": {}, \"duration_ms\": {}, \"status\": {}, \
+                \"error\": {}}}\n",
+                if started { "started" } else { "finished" },
+                command
+                    .started
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis(),
+                json(&feature),
+                json(&scenario),
+                json(&step),
+                json(&command.method),
+                json(&command.endpoint),
+                json(&command.payload),
+                if started { "null".to_string() } else { format!("{duration_ms:.1}") },
+                if started { "null".to_string() } else { command.status.to_string() },
+                command.error.as_deref().map_or("null".to_string(), json),
+            );
+            let written = std::fs::create_dir_all(&dir).and_then(|()| {
+                use std::io::Write as _;
+
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(dir.join("commands.jsonl"))?
+                    .write_all(line.as_bytes())
+            });
+            if let Err(err) = written {
+                Self::__warn(&format!("Failed to write the command log of the scenario \"{scenario}\": {err}"));
+            }
+        }
+    }
+}
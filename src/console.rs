@@ -0,0 +1,230 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Record the console messages, uncaught errors and unhandled rejections of
+/// the page in `window.__worlderConsole` as `[level, message]` pairs, for the
+/// browsers whose driver doesn't expose the logs of the browser.
+const CONSOLE_LISTENER_SCRIPT: &str = "if (!window.__worlderConsole) { \
+    const messages = window.__worlderConsole = []; \
+    const format = (values) => values.map((value) => { \
+        if (value instanceof Error) return value.stack || String(value); \
+        if (typeof value === 'object' && value !== null) { \
+            try { return JSON.stringify(value); } catch (error) {} \
+        } \
+        return String(value); \
+    }).join(' '); \
+    const levels = [['error', 'error'], ['warn', 'warning'], ['info', 'info'], ['log', 'info'], ['debug', 'debug']]; \
+    for (const [method, level] of levels) { \
+        const original = console[method]; \
+        console[method] = function (...values) { \
+            messages.push([level, format(values)]); \
+            return original.apply(this, values); \
+        }; \
+    } \
+    window.addEventListener('error', (event) => { \
+        messages.push(['error', event.error ? format([event.error]) : event.message]); \
+    }); \
+    window.addEventListener('unhandledrejection', (event) => { \
+        messages.push(['error', 'Unhandled rejection: ' + format([event.reason])]); \
+    }); \
+}";
+
+const CONSOLE_DRAIN_SCRIPT: &str =
+    "return window.__worlderConsole ? window.__worlderConsole.splice(0) : [];";
+
+pub(crate) fn build_console_fns(thirtyfour: &syn::Path) -> TokenStream {
+    quote! {
+        #[doc = "Get the policy applied to the console errors of the scenarios."]
+        #[doc = ""]
+        #[doc = "It's defined by the `CONSOLE_ERRORS` environment variable, one of `\"fail\"`,"]
+        #[doc = "`\"warn\"` (default) or `\"ignore\"`. With `\"fail\"`, the scenarios that log errors"]
+        #[doc = "in the console, throw uncaught errors or leave rejections unhandled fail, unless"]
+        #[doc = "the errors match a pattern allowed with"]
+        #[doc = "[`allow_console_error`](Self::allow_console_error)."]
+        #[must_use]
+        pub fn console_errors(&self) -> &str {
+            &self.console_errors
+        }
+
+        #[doc = "Allow the console errors matching the given JavaScript regular expression, like"]
+        #[doc = "known noise from third party scripts."]
+        #[doc = ""]
+        #[doc = "```rust,ignore"]
+        #[doc = "AppWorld::allow_console_error(\"ResizeObserver loop\");"]
+        #[doc = "```"]
+        pub fn allow_console_error(pattern: &str) {
+            Self::__allowed_console_errors()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .push(pattern.to_string());
+        }
+
+        #[doc = "Get the messages logged in the console of the browser during the scenario, as"]
+        #[doc = "`(level, message)` pairs with levels `error`, `warning`, `info` and `debug`."]
+        #[doc = ""]
+        #[doc = "Chrome and Edge report all the messages of the browser. In Firefox, messages are"]
+        #[doc = "collected by a listener injected by the navigation methods of the world once the"]
+        #[doc = "page is loaded, because geckodriver can't run scripts before the ones of the page"]
+        #[doc = "without WebDriver BiDi, which thirtyfour doesn't support. So the messages logged and"]
+        #[doc = "the errors thrown while the page loads, and the ones of the pages reached without"]
+        #[doc = "the navigation methods of the world, like after clicking a link, are missed."]
+        pub async fn console_messages(&self) -> Vec<(String, String)> {
+            self.__collect_console_messages().await;
+            self.console_messages
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .clone()
+        }
+
+        async fn __collect_console_messages(&self) {
+            let messages = if self.browser == "firefox" {
+                self.driver()
+                    .execute(#CONSOLE_DRAIN_SCRIPT, vec![])
+                    .await
+                    .and_then(|ret| ret.convert::<Vec<(String, String)>>())
+            } else {
+                self.__browser_logs().await
+            };
+            match messages {
+                Ok(messages) => self
+                    .console_messages
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .extend(messages),
+                Err(err) => Self::__warn(&format!("Failed to collect the console messages: {err}")),
+            }
+        }
+
+        // Logs of the browser enabled by the `loggingPrefs` capability of Chrome and
+        // Edge, which are not exposed by thirtyfour.
+        async fn __browser_logs(&self) -> Result<Vec<(String, String)>, #thirtyfour::error::WebDriverError> {
+            let mut body = #thirtyfour::Capabilities::new();
+            body.insert("type".to_string(), "browser".into());
+            let logs = self.__post_session_command("se/log", body).await?.value_json()?;
+            Ok(logs
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| {
+                    let level = match entry["level"].as_str()? {
+                        "SEVERE" => "error",
+                        "WARNING" => "warning",
+                        "DEBUG" => "debug",
+                        _ => "info",
+                    };
+                    Some((level.to_string(), entry["message"].as_str()?.to_string()))
+                })
+                .collect())
+        }
+
+        async fn __install_console_listener(&self) {
+            if self.browser == "firefox" {
+                if let Err(err) = self.driver().execute(#CONSOLE_LISTENER_SCRIPT, vec![]).await {
+                    Self::__warn(&format!("Failed to install the console listener: {err}"));
+                }
+            }
+        }
+
+        async fn __before_navigation(&self) {
+            // The messages recorded by the listener are lost when leaving the page.
+            if self.browser == "firefox" {
+                self.__collect_console_messages().await;
+            }
+        }
+
+        // Returns the report of the errors when the scenario must fail because of them.
+        async fn __check_console(&self, dir: &std::path::Path, scenario: &str, failed: bool) -> Option<String> {
+            let messages = self.console_messages().await;
+            let errors = messages
+                .iter()
+                .filter(|(level, _)| level == "error")
+                .map(|(_, message)| message.as_str())
+                .collect::<Vec<_>>();
+            let errors = if errors.is_empty() || self.console_errors == "ignore" {
+                vec![]
+            } else {
+                self.__disallowed_console_errors(&errors).await
+            };
+
+            if failed || !errors.is_empty() {
+                let log = messages
+                    .iter()
+                    .map(|(level, message)| format!("[{level}] {message}\n"))
+                    .collect::<String>();
+                if !log.is_empty() && std::fs::create_dir_all(&dir).is_ok() {
+                    Self::__write_artifact(&dir.join("console.log"), log.as_bytes());
+                }
+            }
+            if errors.is_empty() {
+                return None;
+            }
+            let report = format!(
+                "{} error(s) in the console of the browser during the scenario \"{scenario}\":\n{}",
+                errors.len(),
+                errors
+                    .iter()
+                    .map(|error| format!("  {error}"))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+            if self.console_errors == "fail" {
+                return Some(report);
+            }
+            Self::__warn(&report);
+            None
+        }
+
+        async fn __disallowed_console_errors(&self, errors: &[&str]) -> Vec<String> {
+            let patterns = Self::__allowed_console_errors()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .clone();
+            if patterns.is_empty() {
+                return errors.iter().map(ToString::to_string).collect();
+            }
+            let list = |values: Vec<&str>| {
+                values.into_iter().map(Self::__js_string).collect::<Vec<_>>().join(", ")
+            };
+            let script = format!(
+                "const patterns = [{}].map((pattern) => new RegExp(pattern)); \
+                return [{}].filter((error) => !patterns.some((pattern) => pattern.test(error)));",
+                list(patterns.iter().map(String::as_str).collect()),
+                list(errors.to_vec()),
+            );
+            self.driver()
+                .execute(script, vec![])
+                .await
+                .and_then(|ret| ret.convert::<Vec<String>>())
+                .unwrap_or_else(|err| {
+                    Self::__warn(&format!("Failed to filter the allowed console errors: {err}"));
+                    errors.iter().map(ToString::to_string).collect()
+                })
+        }
+
+        fn __allowed_console_errors() -> &'static std::sync::Mutex<Vec<String>> {
+            static ALLOWED_CONSOLE_ERRORS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(vec![]);
+            &ALLOWED_CONSOLE_ERRORS
+        }
+
+        fn __set_logging_capabilities<C: #thirtyfour::CapabilitiesHelper>(
+            caps: &mut C,
+            capability: &str,
+        ) -> Result<(), String> {
+            let prefs = std::collections::HashMap::from([("browser", "ALL")]);
+            caps.set_base_capability(capability, prefs)
+                .map_err(|err| format!("Failed to set {capability} capability: {err}"))
+        }
+
+        fn __discover_console_errors() -> Result<String, String> {
+            let policy = Self::__config_value("CONSOLE_ERRORS")
+                .map_or("warn".to_string(), |value| value.trim().to_ascii_lowercase());
+            if !matches!(policy.as_str(), "fail" | "warn" | "ignore") {
+                return Err(format!(
+                    "Invalid CONSOLE_ERRORS environment variable value \"{policy}\". \
+                    Expected one of: \"fail\", \"warn\" or \"ignore\"."
+                ));
+            }
+            Ok(policy)
+        }
+    }
+}
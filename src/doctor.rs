@@ -0,0 +1,253 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+pub(crate) fn build_doctor_fns(thirtyfour: &syn::Path) -> TokenStream {
+    quote! {
+        #[doc = "Diagnose the environment in which the world runs."]
+        #[doc = ""]
+        #[doc = "Writes to stderr the resolved configuration with the source of each value, checks"]
+        #[doc = "that the `WebDriver` server at `DRIVER_URL` is ready and reports its version, then"]
+        #[doc = "creates a session with each browser to report its version and check that it can"]
+        #[doc = "load `HOST_URL`. Every problem found is followed by a hint to fix it."]
+        #[doc = ""]
+        #[doc = "Returns `true` if no problems have been found."]
+        pub async fn doctor() -> bool {
+            let mut ok = true;
+
+            match Self::__env_file() {
+                Ok(Some((path, _))) => eprintln!("Environment file: {}", path.display()),
+                Ok(None) => {}
+                Err(err) => {
+                    eprintln!("Environment file: error: {err}");
+                    return false;
+                }
+            }
+            eprintln!("Resolved configuration:");
+            let values: [(&str, fn() -> Result<String, String>); 25] = [
+                ("BROWSER", || Self::__discover_browsers().map(|browsers| browsers.join(","))),
+                ("DRIVER_URL", || Ok(Self::__discover_driver_url())),
+                ("HOST_URL", || Ok(Self::__discover_host_url())),
+                ("HEADLESS", || Self::__discover_headless().map(|headless| headless.to_string())),
+                ("WINDOW_SIZE", || {
+                    Self::__discover_window_size().map(|(width, height, device_pixel_ratio)| {
+                        format!("{width}x{height}@{device_pixel_ratio}")
+                    })
+                }),
+                ("READY_TIMEOUT", || Self::__discover_ready_timeout().map(|timeout| format!("{timeout:?}"))),
+                ("EXPECT_TIMEOUT", || Self::__discover_expect_timeout().map(|timeout| format!("{timeout:?}"))),
+                ("PAGE_LOAD_TIMEOUT", || Self::__discover_timeouts().map(|timeouts| format!("{:?}", timeouts.0))),
+                ("SCRIPT_TIMEOUT", || Self::__discover_timeouts().map(|timeouts| format!("{:?}", timeouts.1))),
+                ("IMPLICIT_WAIT", || Self::__discover_timeouts().map(|timeouts| format!("{:?}", timeouts.2))),
+                ("PAGE_LOAD_STRATEGY", Self::__discover_page_load_strategy),
+                ("ARTIFACTS_DIR", || Ok(Self::__discover_artifacts_dir().display().to_string())),
+                ("SCREENSHOT_ON_FAILURE", Self::__discover_screenshot_on_failure),
+                ("WORLDER_LOG", Self::__discover_log_level),
+                ("SNAPSHOT_ON_FAILURE", || Self::__discover_snapshot_on_failure().map(|snapshot| snapshot.to_string())),
+                ("SNAPSHOT_MAX_SIZE", || Self::__discover_snapshot_max_size().map(|size| format!("{size} bytes"))),
+                ("CONSOLE_ERRORS", Self::__discover_console_errors),
+                ("JSON_REPORT", || {
+                    Ok(Self::__discover_json_report().map_or("off".to_string(), |path| path.display().to_string()))
+                }),
+                ("HTML_REPORT", || {
+                    Ok(Self::__discover_html_report().map_or("off".to_string(), |path| path.display().to_string()))
+                }),
+                ("JUNIT_REPORT", || {
+                    Ok(Self::__discover_junit_report().map_or("off".to_string(), |path| path.display().to_string()))
+                }),
+                ("ALLURE_RESULTS", || {
+                    Ok(Self::__discover_allure_results().map_or("off".to_string(), |path| path.display().to_string()))
+                }),
+                ("TRACE", Self::__discover_trace),
+                ("SCREENCAST", Self::__discover_screencast),
+                ("SCREENCAST_FPS", || Self::__discover_screencast_fps().map(|fps| fps.to_string())),
+                ("COMMAND_LOG", Self::__discover_command_log),
+            ];
+            for (name, discover) in values {
+                match discover() {
+                    Ok(value) => eprintln!(
+                        "  {name:<21} {value} ({})",
+                        Self::__config_source(name),
+                    ),
+                    Err(err) => {
+                        ok = false;
+                        eprintln!("  {name:<21} error: {err}");
+                    }
+                }
+            }
+            let hosts = Self::__discover_hosts();
+            for name in hosts.keys() {
+                let variable = format!("HOST_URL_{}", name.to_ascii_uppercase());
+                eprintln!(
+                    "  {variable:<21} {} ({})",
+                    hosts[name],
+                    Self::__config_source(&variable),
+                );
+            }
+            let Ok(browsers) = Self::__discover_browsers() else {
+                return false;
+            };
+            let driver_url = Self::__discover_driver_url();
+            let host_url = Self::__discover_host_url();
+
+            // Ask the driver for its status before creating any session, so an
+            // unreachable driver is not reported as a failure of the browser.
+            if driver_url.starts_with("http://") {
+                match Self::__driver_status(&driver_url) {
+                    Ok((200, body)) => {
+                        let message = Self::__json_string_field(&body, "message")
+                            .filter(|message| !message.is_empty())
+                            .map(|message| format!(" ({})", message.trim_end_matches('.')))
+                            .unwrap_or_default();
+                        // The status has no version, but most drivers add it to the build.
+                        let version = Self::__json_string_field(&body, "version")
+                            .map(|version| format!(", version {version}"))
+                            .unwrap_or_default();
+                        if body.split_whitespace().collect::<String>().contains("\"ready\":true") {
+                            eprintln!("Driver at {driver_url}: ready{message}{version}");
+                        } else {
+                            ok = false;
+                            eprintln!("Driver at {driver_url}: not ready{message}{version}");
+                            eprintln!(
+                                "  hint: the driver is running but can't create new sessions. \
+                                Close the sessions left open by previous runs or restart it."
+                            );
+                        }
+                    }
+                    Ok((status, _)) => {
+                        eprintln!("Driver at {driver_url}: unexpected HTTP status {status} from /status");
+                        eprintln!("  hint: make sure that DRIVER_URL points to a WebDriver server.");
+                        return false;
+                    }
+                    Err(err) => {
+                        eprintln!("Driver at {driver_url}: unreachable ({err})");
+                        let commands = browsers
+                            .iter()
+                            .map(|browser| match browser.as_str() {
+                                "firefox" => "`geckodriver --port=4444`",
+                                "edge" => "`msedgedriver --port=4444`",
+                                _ => "`chromedriver --port=4444`",
+                            })
+                            .collect::<Vec<_>>()
+                            .join(" or ");
+                        eprintln!(
+                            "  hint: start a WebDriver server with {commands} or set DRIVER_URL \
+                            to the URL of a running one."
+                        );
+                        return false;
+                    }
+                }
+            } else {
+                eprintln!("Driver at {driver_url}: status not checked, only http:// URLs are supported");
+            }
+
+            for browser in browsers {
+                *Self::__matrix_browser()
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(browser.clone());
+                let world = match Self::__build_driver().await {
+                    Ok(world) => world,
+                    Err(err) => {
+                        ok = false;
+                        eprintln!("Browser {browser}: failed to create a session ({err})");
+                        continue;
+                    }
+                };
+                match world
+                    .driver
+                    .execute("return navigator.userAgent;", vec![])
+                    .await
+                    .and_then(|ret| ret.convert::<String>())
+                {
+                    Ok(user_agent) => eprintln!("Browser {browser}: {user_agent}"),
+                    Err(err) => {
+                        ok = false;
+                        eprintln!("Browser {browser}: failed to get the user agent ({err})");
+                    }
+                }
+
+                // The hosts are loaded by the browser, which may not run in this machine.
+                for host_url in std::iter::once(&host_url).chain(hosts.values()) {
+                    match world.driver.goto(host_url).await {
+                        Ok(()) => eprintln!("Host at {host_url}: reachable from {browser}"),
+                        Err(err) => {
+                            ok = false;
+                            eprintln!("Host at {host_url}: unreachable from {browser} ({err})");
+                            eprintln!(
+                                "  hint: serve the application under test at {host_url} or \
+                                change the environment variable that defines it."
+                            );
+                        }
+                    }
+                }
+                if let Err(err) = #thirtyfour::WebDriver::quit(world.driver).await {
+                    eprintln!("Browser {browser}: failed to close the session ({err})");
+                }
+            }
+            *Self::__matrix_browser()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner) = None;
+
+            ok
+        }
+
+        #[doc = "Diagnose the environment in which the world runs and exit with a non-zero code"]
+        #[doc = "if any problem has been found."]
+        #[doc = ""]
+        #[doc = "See [`doctor`](Self::doctor) for details."]
+        pub async fn doctor_and_exit() {
+            if !Self::doctor().await {
+                std::process::exit(1);
+            }
+        }
+
+        // Blocking `GET /status` sent with HTTP/1.0, so the response is neither
+        // chunked nor kept alive. Sessions are not needed to get the status and
+        // `thirtyfour` can't send commands without one.
+        fn __driver_status(driver_url: &str) -> Result<(u16, String), String> {
+            use std::io::{Read as _, Write as _};
+
+            let rest = driver_url
+                .trim_end_matches('/')
+                .strip_prefix("http://")
+                .ok_or_else(|| format!("unsupported URL {driver_url}"))?;
+            let (authority, base_path) = rest
+                .find('/')
+                .map_or((rest, ""), |index| rest.split_at(index));
+            let address = if authority.contains(':') {
+                authority.to_string()
+            } else {
+                format!("{authority}:80")
+            };
+            let socket_address = std::net::ToSocketAddrs::to_socket_addrs(&address)
+                .map_err(|err| format!("failed to resolve {authority}: {err}"))?
+                .next()
+                .ok_or_else(|| format!("failed to resolve {authority}"))?;
+
+            let timeout = std::time::Duration::from_secs(10);
+            let mut stream = std::net::TcpStream::connect_timeout(&socket_address, timeout)
+                .map_err(|err| format!("failed to connect to {authority}: {err}"))?;
+            stream
+                .set_read_timeout(Some(timeout))
+                .map_err(|err| err.to_string())?;
+            write!(
+                stream,
+                "GET {base_path}/status HTTP/1.0\r\nHost: {authority}\r\nAccept: application/json\r\n\r\n",
+            )
+            .map_err(|err| format!("failed to send request to {authority}: {err}"))?;
+            let mut response = String::new();
+            stream
+                .read_to_string(&mut response)
+                .map_err(|err| format!("failed to read response from {authority}: {err}"))?;
+
+            let (head, body) = response
+                .split_once("\r\n\r\n")
+                .ok_or_else(|| format!("malformed HTTP response from {authority}"))?;
+            let status = head
+                .split(' ')
+                .nth(1)
+                .and_then(|status| status.parse::<u16>().ok())
+                .ok_or_else(|| format!("malformed HTTP response from {authority}"))?;
+            Ok((status, body.to_string()))
+        }
+    }
+}
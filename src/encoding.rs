@@ -0,0 +1,115 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Encoding helpers shared by the features of the world.
+///
+/// The generated world can't assume any serialization crate in the
+/// dependencies of the user, so the few formats it reads and writes are
+/// handled by hand here.
+pub(crate) fn build_encoding_fns() -> TokenStream {
+    quote! {
+        fn __json_string_field(json: &str, key: &str) -> Option<String> {
+            let start = json.find(&format!("\"{key}\""))? + key.len() + 2;
+            let value = json[start..].trim_start().strip_prefix(':')?.trim_start();
+            let mut chars = value.strip_prefix('"')?.chars();
+            let mut result = String::new();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => return Some(result),
+                    '\\' => match chars.next()? {
+                        'n' => result.push('\n'),
+                        't' => result.push('\t'),
+                        'r' => result.push('\r'),
+                        'u' => {
+                            let code = chars.by_ref().take(4).collect::<String>();
+                            result.push(
+                                u32::from_str_radix(&code, 16)
+                                    .ok()
+                                    .and_then(char::from_u32)
+                                    .unwrap_or(char::REPLACEMENT_CHARACTER),
+                            );
+                        }
+                        other => result.push(other),
+                    },
+                    c => result.push(c),
+                }
+            }
+            None
+        }
+
+        fn __json_string(value: &str) -> String {
+            use std::fmt::Write as _;
+
+            let mut escaped = String::with_capacity(value.len() + 2);
+            escaped.push('"');
+            for c in value.chars() {
+                match c {
+                    '"' => escaped.push_str("\\\""),
+                    '\\' => escaped.push_str("\\\\"),
+                    '\n' => escaped.push_str("\\n"),
+                    '\r' => escaped.push_str("\\r"),
+                    '\t' => escaped.push_str("\\t"),
+                    c if c.is_control() => {
+                        _ = write!(escaped, "\\u{:04x}", u32::from(c));
+                    }
+                    c => escaped.push(c),
+                }
+            }
+            escaped.push('"');
+            escaped
+        }
+
+        fn __base64_encode(content: &[u8]) -> String {
+            const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+            let mut encoded = String::with_capacity(content.len().div_ceil(3) * 4);
+            for chunk in content.chunks(3) {
+                let buffer = chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0u32, |buffer, (index, byte)| buffer | u32::from(*byte) << (16 - 8 * index));
+                for index in 0..4 {
+                    if index <= chunk.len() {
+                        encoded.push(char::from(ALPHABET[(buffer >> (18 - 6 * index) & 0x3f) as usize]));
+                    } else {
+                        encoded.push('=');
+                    }
+                }
+            }
+            encoded
+        }
+
+        fn __base64_decode(value: &str) -> Option<Vec<u8>> {
+            let mut decoded = Vec::with_capacity(value.len() / 4 * 3);
+            let (mut buffer, mut bits) = (0u32, 0u32);
+            for c in value.bytes().filter(|c| *c != b'=' && !c.is_ascii_whitespace()) {
+                let sextet = match c {
+                    b'A'..=b'Z' => c - b'A',
+                    b'a'..=b'z' => c - b'a' + 26,
+                    b'0'..=b'9' => c - b'0' + 52,
+                    b'+' => 62,
+                    b'/' => 63,
+                    _ => return None,
+                };
+                buffer = ((buffer << 6) | u32::from(sextet)) & 0xffff;
+                bits += 6;
+                if bits >= 8 {
+                    bits -= 8;
+                    decoded.push((buffer >> bits).to_le_bytes()[0]);
+                }
+            }
+            Some(decoded)
+        }
+
+        fn __crc32(content: &[u8]) -> u32 {
+            let mut crc = !0u32;
+            for byte in content {
+                crc ^= u32::from(*byte);
+                for _ in 0..8 {
+                    crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+                }
+            }
+            !crc
+        }
+    }
+}
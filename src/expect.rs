@@ -0,0 +1,205 @@
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use quote::quote;
+
+pub(crate) fn build_expect_struct(
+    thirtyfour: &syn::Path,
+    vis_ident: &TokenTree,
+    struct_name_ident: &TokenTree,
+    expect_ident: &Ident,
+    locator_ident: &Ident,
+) -> TokenStream {
+    quote! {
+        #[doc = "Expectations about the elements matching a locator, created by the `expect` method of the world."]
+        #[doc = ""]
+        #[doc = "Each expectation is retried until it's met or the timeout expires, so steps don't"]
+        #[doc = "need to wait for the page to settle."]
+        #[derive(Debug, Clone)]
+        #vis_ident struct #expect_ident<'a> {
+            world: &'a #struct_name_ident,
+            locator: #locator_ident,
+            timeout: std::time::Duration,
+        }
+
+        impl #expect_ident<'_> {
+            #[doc = "Set the timeout of the expectation, which defaults to the `expect_timeout` of the world."]
+            #[must_use]
+            pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+                self.timeout = timeout;
+                self
+            }
+
+            #[doc = "Expect the first element matching the locator to be visible."]
+            #[doc = ""]
+            #[doc = "# Panics"]
+            #[doc = ""]
+            #[doc = "If the expectation is not met before the timeout."]
+            pub async fn to_be_visible(&self) {
+                self.__retry("to be visible", || async {
+                    let element = self.world.locate(self.locator.clone()).await?;
+                    let visible = element.is_displayed().await?;
+                    let observed = if visible { "visible" } else { "hidden" };
+                    Ok((visible, observed.to_string()))
+                })
+                .await;
+            }
+
+            #[doc = "Expect the first element matching the locator to be enabled."]
+            #[doc = ""]
+            #[doc = "# Panics"]
+            #[doc = ""]
+            #[doc = "If the expectation is not met before the timeout."]
+            pub async fn to_be_enabled(&self) {
+                self.__retry("to be enabled", || async {
+                    let element = self.world.locate(self.locator.clone()).await?;
+                    let enabled = element.is_enabled().await?;
+                    let observed = if enabled { "enabled" } else { "disabled" };
+                    Ok((enabled, observed.to_string()))
+                })
+                .await;
+            }
+
+            #[doc = "Expect the text of the first element matching the locator to be the given one."]
+            #[doc = ""]
+            #[doc = "Whitespaces are normalized before comparing the texts."]
+            #[doc = ""]
+            #[doc = "# Panics"]
+            #[doc = ""]
+            #[doc = "If the expectation is not met before the timeout."]
+            pub async fn to_have_text(&self, expected: &str) {
+                let expected = expected.split_whitespace().collect::<Vec<_>>().join(" ");
+                self.__retry(&format!("to have text \"{expected}\""), || async {
+                    let element = self.world.locate(self.locator.clone()).await?;
+                    let text = element.text().await?;
+                    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    Ok((text == expected, format!("text \"{text}\"")))
+                })
+                .await;
+            }
+
+            #[doc = "Expect the text of the first element matching the locator to contain the given one."]
+            #[doc = ""]
+            #[doc = "Whitespaces are normalized before comparing the texts."]
+            #[doc = ""]
+            #[doc = "# Panics"]
+            #[doc = ""]
+            #[doc = "If the expectation is not met before the timeout."]
+            pub async fn to_contain_text(&self, expected: &str) {
+                let expected = expected.split_whitespace().collect::<Vec<_>>().join(" ");
+                self.__retry(&format!("to contain text \"{expected}\""), || async {
+                    let element = self.world.locate(self.locator.clone()).await?;
+                    let text = element.text().await?;
+                    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    Ok((text.contains(&expected), format!("text \"{text}\"")))
+                })
+                .await;
+            }
+
+            #[doc = "Expect the first element matching the locator to have an attribute with the given value."]
+            #[doc = ""]
+            #[doc = "# Panics"]
+            #[doc = ""]
+            #[doc = "If the expectation is not met before the timeout."]
+            pub async fn to_have_attribute(&self, name: &str, value: &str) {
+                self.__retry(&format!("to have attribute {name}=\"{value}\""), || async {
+                    let element = self.world.locate(self.locator.clone()).await?;
+                    Ok(match element.attr(name).await? {
+                        Some(actual) => (actual == value, format!("{name}=\"{actual}\"")),
+                        None => (false, format!("without attribute {name}")),
+                    })
+                })
+                .await;
+            }
+
+            #[doc = "Expect the given number of elements to match the locator."]
+            #[doc = ""]
+            #[doc = "# Panics"]
+            #[doc = ""]
+            #[doc = "If the expectation is not met before the timeout."]
+            pub async fn to_have_count(&self, count: usize) {
+                self.__retry(&format!("to have count {count}"), || async {
+                    let elements = self.world.locate_all(self.locator.clone()).await?;
+                    Ok((elements.len() == count, format!("count {}", elements.len())))
+                })
+                .await;
+            }
+
+            async fn __retry<F, Fut>(&self, expectation: &str, check: F)
+            where
+                F: Fn() -> Fut,
+                Fut: std::future::Future<Output = Result<(bool, String), #thirtyfour::error::WebDriverError>>,
+            {
+                let deadline = std::time::Instant::now() + self.timeout;
+                let observed = loop {
+                    let observed = match check().await {
+                        Ok((true, _)) => return,
+                        Ok((false, observed)) => observed,
+                        Err(err) => format!("not found ({err})"),
+                    };
+                    if std::time::Instant::now() >= deadline {
+                        break observed;
+                    }
+                    #thirtyfour::support::sleep(std::time::Duration::from_millis(100)).await;
+                };
+
+                let excerpt = match self.world.locate(self.locator.clone()).await {
+                    Ok(element) => element.outer_html().await.ok(),
+                    Err(_) => self
+                        .world
+                        .driver()
+                        .execute("return document.body ? document.body.outerHTML : '';", vec![])
+                        .await
+                        .and_then(|ret| ret.convert::<String>())
+                        .ok(),
+                }
+                .unwrap_or_default();
+                let excerpt = if excerpt.chars().count() > 1000 {
+                    format!("{}...", excerpt.chars().take(1000).collect::<String>())
+                } else {
+                    excerpt
+                };
+                panic!(
+                    "Expected {:?} {expectation} within {:?}, but the last observed value was: {observed}\n\
+                    DOM excerpt:\n{excerpt}",
+                    self.locator,
+                    self.timeout,
+                );
+            }
+        }
+    }
+}
+
+pub(crate) fn build_expect_fns(expect_ident: &Ident, locator_ident: &Ident) -> TokenStream {
+    quote! {
+        #[doc = "Create expectations about the elements matching the given locator, a `By` or a"]
+        #[doc = "locator built with [`by_role`](Self::by_role), [`by_label`](Self::by_label),"]
+        #[doc = "[`by_placeholder`](Self::by_placeholder) or [`by_text`](Self::by_text)."]
+        #[doc = ""]
+        #[doc = "```rust,ignore"]
+        #[doc = "world.expect(By::Css(\"h1\")).to_have_text(\"Welcome\").await;"]
+        #[doc = "world.expect(AppWorld::by_role(\"alert\", None)).to_be_visible().await;"]
+        #[doc = "```"]
+        #[must_use]
+        pub fn expect(&self, locator: impl Into<#locator_ident>) -> #expect_ident<'_> {
+            #expect_ident {
+                world: self,
+                locator: locator.into(),
+                timeout: self.expect_timeout,
+            }
+        }
+
+        #[doc = "Get the timeout of the expectations of the world."]
+        #[doc = ""]
+        #[doc = "It's defined by the `EXPECT_TIMEOUT` environment variable, which defaults to `5s`."]
+        #[must_use]
+        pub fn expect_timeout(&self) -> std::time::Duration {
+            self.expect_timeout
+        }
+
+        fn __discover_expect_timeout() -> Result<std::time::Duration, String> {
+            Self::__config_value("EXPECT_TIMEOUT").map_or(
+                Ok(std::time::Duration::from_secs(5)),
+                |value| Self::__parse_duration("EXPECT_TIMEOUT", &value),
+            )
+        }
+    }
+}
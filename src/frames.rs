@@ -0,0 +1,57 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+pub(crate) fn build_frames_fns(thirtyfour: &syn::Path) -> TokenStream {
+    quote! {
+        #[doc = "Run a closure inside the first iframe matching the given locator."]
+        #[doc = ""]
+        #[doc = "The browser is switched back to the parent frame when the future returned by the"]
+        #[doc = "closure completes, even if it returns an error or panics, so the next steps are not"]
+        #[doc = "left inside the iframe. Unlike switching to the default content, this keeps the"]
+        #[doc = "browser inside the outer iframe when calls to `within_frame` are nested, and is"]
+        #[doc = "the same when they're not."]
+        #[doc = ""]
+        #[doc = "```rust,ignore"]
+        #[doc = "world"]
+        #[doc = "    .within_frame(By::Css(\"iframe#payment\"), || async {"]
+        #[doc = "        world.find_by_label(\"Card number\").await?.send_keys(\"4242\").await"]
+        #[doc = "    })"]
+        #[doc = "    .await??;"]
+        #[doc = "```"]
+        pub async fn within_frame<F, Fut, T>(
+            &self,
+            frame: impl Into<#thirtyfour::By>,
+            f: F,
+        ) -> Result<T, #thirtyfour::error::WebDriverError>
+        where
+            F: FnOnce() -> Fut,
+            Fut: std::future::Future<Output = T>,
+        {
+            struct CatchUnwind<Fut>(std::pin::Pin<Box<Fut>>);
+
+            impl<Fut: std::future::Future> std::future::Future for CatchUnwind<Fut> {
+                type Output = std::thread::Result<Fut::Output>;
+
+                fn poll(
+                    mut self: std::pin::Pin<&mut Self>,
+                    cx: &mut std::task::Context<'_>,
+                ) -> std::task::Poll<Self::Output> {
+                    let future = self.0.as_mut();
+                    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| future.poll(cx))) {
+                        Ok(std::task::Poll::Pending) => std::task::Poll::Pending,
+                        Ok(std::task::Poll::Ready(output)) => std::task::Poll::Ready(Ok(output)),
+                        Err(payload) => std::task::Poll::Ready(Err(payload)),
+                    }
+                }
+            }
+
+            self.driver().find(frame.into()).await?.enter_frame().await?;
+            let output = CatchUnwind(Box::pin(f())).await;
+            let switched = self.driver().enter_parent_frame().await;
+            match output {
+                Ok(output) => switched.map(|()| output),
+                Err(payload) => std::panic::resume_unwind(payload),
+            }
+        }
+    }
+}
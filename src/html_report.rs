@@ -0,0 +1,248 @@
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use quote::quote;
+
+const STYLE: &str = "body { font-family: system-ui, sans-serif; margin: 0; color: #1f2328; } \
+    header { background: #f6f8fa; border-bottom: 1px solid #d0d7de; padding: 1em 2em; } \
+    main { padding: 1em 2em; } \
+    h1 { margin: 0 0 .5em; font-size: 1.5em; } \
+    h2 { font-size: 1.2em; margin: 1.5em 0 .5em; } \
+    .metadata th { text-align: left; padding-right: 1em; font-weight: 600; } \
+    .filters { margin-top: 1em; display: flex; gap: 1em; align-items: center; } \
+    details.scenario { border: 1px solid #d0d7de; border-left-width: 4px; border-radius: 4px; margin: .5em 0; padding: .5em 1em; } \
+    details.scenario.passed { border-left-color: #1a7f37; } \
+    details.scenario.failed { border-left-color: #cf222e; } \
+    details.scenario.skipped { border-left-color: #9a6700; } \
+    summary { cursor: pointer; } \
+    .status { display: inline-block; min-width: 4.5em; font-weight: 600; text-transform: uppercase; font-size: .8em; } \
+    .passed > .status, .passed > summary > .status { color: #1a7f37; } \
+    .failed > .status, .failed > summary > .status { color: #cf222e; } \
+    .skipped > .status, .skipped > summary > .status { color: #9a6700; } \
+    .duration, .tags { color: #656d76; font-size: .85em; margin-left: .5em; } \
+    .keyword { font-weight: 600; } \
+    ol.steps { list-style: none; padding-left: 0; } \
+    ol.steps li { margin: .3em 0; } \
+    pre { background: #f6f8fa; padding: .5em; overflow: auto; white-space: pre-wrap; } \
+    pre.error { color: #cf222e; } \
+    .attachments img { max-width: 100%; border: 1px solid #d0d7de; margin: .5em 0; } \
+    .attachments iframe { width: 100%; height: 30em; border: 1px solid #d0d7de; } \
+    .hidden { display: none; }";
+
+const SCRIPT: &str = "const filter = () => { \
+        const statuses = [...document.querySelectorAll('[data-filter-status]')] \
+            .filter((input) => input.checked) \
+            .map((input) => input.dataset.filterStatus); \
+        const tag = document.getElementById('filter-tag').value; \
+        for (const scenario of document.querySelectorAll('details.scenario')) { \
+            const visible = statuses.includes(scenario.dataset.status) \
+                && (!tag || scenario.dataset.tags.split(' ').includes(tag)); \
+            scenario.classList.toggle('hidden', !visible); \
+        } \
+        for (const feature of document.querySelectorAll('section.feature')) { \
+            feature.classList.toggle('hidden', !feature.querySelector('details.scenario:not(.hidden)')); \
+        } \
+    }; \
+    for (const input of document.querySelectorAll('.filters input, .filters select')) { \
+        input.addEventListener('change', filter); \
+    }";
+
+pub(crate) fn build_html_report_fns(
+    struct_name_ident: &TokenTree,
+    report_ident: &Ident,
+) -> TokenStream {
+    quote! {
+        impl #report_ident {
+            #[doc = "Render the report as a self-contained HTML page, with the configuration of the"]
+            #[doc = "world, the artifacts of the steps embedded and filters by status and tag."]
+            #[must_use]
+            pub fn to_html(&self) -> String {
+                use std::fmt::Write as _;
+
+                let escape = #struct_name_ident::__html_escape;
+                let scenarios = self.features.iter().flat_map(|feature| &feature.scenarios);
+                let mut counts = [("passed", 0), ("failed", 0), ("skipped", 0)];
+                let mut all_tags = std::collections::BTreeSet::new();
+                for scenario in scenarios {
+                    if let Some((_, count)) = counts.iter_mut().find(|(status, _)| *status == scenario.status()) {
+                        *count += 1;
+                    }
+                }
+
+                let mut main = String::new();
+                for feature in &self.features {
+                    _ = write!(
+                        main,
+                        "<section class=\"feature\"><h2>{}: {}</h2>",
+                        escape(&feature.keyword),
+                        escape(&feature.name),
+                    );
+                    if !feature.description.trim().is_empty() {
+                        _ = write!(main, "<p>{}</p>", escape(feature.description.trim()));
+                    }
+                    for scenario in &feature.scenarios {
+                        let tags = feature
+                            .tags
+                            .iter()
+                            .chain(&scenario.tags)
+                            .map(|tag| format!("@{tag}"))
+                            .collect::<Vec<_>>();
+                        all_tags.extend(tags.iter().cloned());
+                        let status = scenario.status();
+                        let rule = scenario
+                            .rule
+                            .as_ref()
+                            .map(|rule| format!("{} &rsaquo; ", escape(rule)))
+                            .unwrap_or_default();
+                        _ = write!(
+                            main,
+                            "<details class=\"scenario {status}\" data-status=\"{status}\" data-tags=\"{}\"{}>\
+                            <summary><span class=\"status\">{status}</span> {rule}{}: {} \
+                            <span class=\"duration\">{:.2?}</span> <span class=\"tags\">{}</span></summary>\
+                            <ol class=\"steps\">",
+                            escape(&tags.join(" ")),
+                            if status == "failed" { " open" } else { "" },
+                            escape(&scenario.keyword),
+                            escape(&scenario.name),
+                            scenario.duration,
+                            escape(&tags.join(" ")),
+                        );
+                        for step in &scenario.steps {
+                            // Hooks are only worth showing when they fail.
+                            let hook = step.section == "before" || step.section == "after";
+                            if hook && step.status != "failed" {
+                                continue;
+                            }
+                            _ = write!(
+                                main,
+                                "<li class=\"{}\"><span class=\"status\">{}</span> \
+                                <span class=\"keyword\">{}</span>{} <span class=\"duration\">{:.2?}</span>",
+                                step.status,
+                                step.status,
+                                escape(&step.keyword),
+                                escape(&step.name),
+                                step.duration,
+                            );
+                            if let Some(error) = &step.error {
+                                _ = write!(main, "<pre class=\"error\">{}</pre>", escape(error));
+                            }
+                            if !step.attachments.is_empty() {
+                                main.push_str("<div class=\"attachments\">");
+                                for path in &step.attachments {
+                                    main.push_str(&Self::__html_attachment(path));
+                                }
+                                main.push_str("</div>");
+                            }
+                            main.push_str("</li>");
+                        }
+                        main.push_str("</ol></details>");
+                    }
+                    main.push_str("</section>");
+                }
+
+                let metadata = #struct_name_ident::__report_metadata()
+                    .iter()
+                    .map(|(name, value)| format!("<tr><th>{name}</th><td>{}</td></tr>", escape(value)))
+                    .collect::<String>();
+                let filters = counts
+                    .iter()
+                    .map(|(status, count)| {
+                        format!(
+                            "<label><input type=\"checkbox\" data-filter-status=\"{status}\" checked> \
+                            {status} ({count})</label>"
+                        )
+                    })
+                    .collect::<String>();
+                let tag_options = all_tags
+                    .iter()
+                    .map(|tag| format!("<option>{}</option>", escape(tag)))
+                    .collect::<String>();
+                format!(
+                    "<!DOCTYPE html>\n<html lang=\"en\"><head><meta charset=\"utf-8\">\
+                    <title>Cucumber report</title><style>{}</style></head><body>\
+                    <header><h1>Cucumber report</h1><table class=\"metadata\">{metadata}</table>\
+                    <div class=\"filters\">{filters}<select id=\"filter-tag\">\
+                    <option value=\"\">All tags</option>{tag_options}</select></div></header>\
+                    <main>{main}</main><script>{}</script></body></html>\n",
+                    #STYLE,
+                    #SCRIPT,
+                )
+            }
+
+            fn __html_attachment(path: &std::path::Path) -> String {
+                let escape = #struct_name_ident::__html_escape;
+                let name = escape(&path.file_name().unwrap_or_default().to_string_lossy());
+                let content = match std::fs::read(path) {
+                    Ok(content) => content,
+                    Err(err) => {
+                        #struct_name_ident::__warn(&format!(
+                            "Failed to embed the artifact {}: {err}",
+                            path.display(),
+                        ));
+                        return String::new();
+                    }
+                };
+                let mime_type = #struct_name_ident::__mime_type(path);
+                match mime_type {
+                    "image/png" | "image/gif" | "image/webp" => format!(
+                        "<img alt=\"{name}\" src=\"data:{mime_type};base64,{}\">",
+                        #struct_name_ident::__base64_encode(&content),
+                    ),
+                    "text/html" => format!(
+                        "<details><summary>{name}</summary><iframe sandbox srcdoc=\"{}\"></iframe></details>",
+                        escape(&String::from_utf8_lossy(&content)),
+                    ),
+                    "text/plain" | "application/json" => format!(
+                        "<details><summary>{name}</summary><pre>{}</pre></details>",
+                        escape(&String::from_utf8_lossy(&content)),
+                    ),
+                    _ => format!(
+                        "<p><a download=\"{name}\" href=\"data:{mime_type};base64,{}\">{name}</a></p>",
+                        #struct_name_ident::__base64_encode(&content),
+                    ),
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn build_html_report_world_fns() -> TokenStream {
+    quote! {
+        fn __html_escape(value: &str) -> String {
+            let mut escaped = String::with_capacity(value.len());
+            for c in value.chars() {
+                match c {
+                    '&' => escaped.push_str("&amp;"),
+                    '<' => escaped.push_str("&lt;"),
+                    '>' => escaped.push_str("&gt;"),
+                    '"' => escaped.push_str("&quot;"),
+                    '\'' => escaped.push_str("&#39;"),
+                    c => escaped.push(c),
+                }
+            }
+            escaped
+        }
+
+        // Configuration of the world shown in the reports. The invalid values, which
+        // fail the creation of the worlds, are left out.
+        fn __report_metadata() -> Vec<(&'static str, String)> {
+            [
+                ("browser", Self::__discover_browser()),
+                ("host_url", Ok(Self::__discover_host_url())),
+                ("driver_url", Ok(Self::__discover_driver_url())),
+                ("headless", Self::__discover_headless().map(|headless| headless.to_string())),
+                (
+                    "window_size",
+                    Self::__discover_window_size().map(|(width, height, device_pixel_ratio)| {
+                        format!("{width}x{height}@{device_pixel_ratio}")
+                    }),
+                ),
+            ]
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value.ok()?)))
+            .collect()
+        }
+
+        fn __discover_html_report() -> Option<std::path::PathBuf> {
+            Self::__report_path("HTML_REPORT")
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use quote::quote;
+
+pub(crate) fn build_junit_report_fns(
+    struct_name_ident: &TokenTree,
+    report_ident: &Ident,
+) -> TokenStream {
+    quote! {
+        impl #report_ident {
+            #[doc = "Render the report in the JUnit XML format."]
+            #[doc = ""]
+            #[doc = "There's a test suite per feature, named after the feature prefixed by the browser"]
+            #[doc = "so the runs of a browser matrix don't collide, with the configuration of the world"]
+            #[doc = "as properties. The output of each test case lists the paths of its artifacts."]
+            #[must_use]
+            pub fn to_junit_xml(&self) -> String {
+                use std::fmt::Write as _;
+
+                let escape = #struct_name_ident::__xml_escape;
+                let metadata = #struct_name_ident::__report_metadata();
+                let browser = metadata
+                    .iter()
+                    .find(|(name, _)| *name == "browser")
+                    .map(|(_, value)| value.clone())
+                    .unwrap_or_default();
+                let properties = metadata
+                    .iter()
+                    .map(|(name, value)| format!("      <property name=\"{name}\" value=\"{}\"/>\n", escape(value)))
+                    .collect::<String>();
+
+                let mut suites = String::new();
+                let mut totals = [0; 3];
+                let mut total_time = std::time::Duration::ZERO;
+                for feature in &self.features {
+                    let suite_name = escape(&format!("{browser}: {}", feature.name));
+                    let mut cases = String::new();
+                    let mut counts = [0; 3];
+                    let mut time = std::time::Duration::ZERO;
+                    for scenario in &feature.scenarios {
+                        counts[0] += 1;
+                        time += scenario.duration;
+                        let rule = scenario.rule.as_ref().map(|rule| format!("{rule}: ")).unwrap_or_default();
+                        _ = writeln!(
+                            cases,
+                            "    <testcase classname=\"{suite_name}\" name=\"{}\" time=\"{:.3}\">",
+                            escape(&format!("{rule}{}: {}: {}", scenario.keyword, scenario.line, scenario.name)),
+                            scenario.duration.as_secs_f64(),
+                        );
+                        match scenario.status() {
+                            "failed" => {
+                                counts[1] += 1;
+                                let step = scenario.steps.iter().find(|step| step.status == "failed");
+                                let error = step.and_then(|step| step.error.clone()).unwrap_or_default();
+                                _ = writeln!(
+                                    cases,
+                                    "      <failure message=\"{}\" type=\"{}\">{}</failure>",
+                                    escape(error.lines().next().unwrap_or_default()),
+                                    step.map_or("step", |step| step.section),
+                                    escape(&format!(
+                                        "{}{}\n{error}",
+                                        step.map(|step| step.keyword.as_str()).unwrap_or_default(),
+                                        step.map(|step| step.name.as_str()).unwrap_or_default(),
+                                    )),
+                                );
+                            }
+                            "skipped" => {
+                                counts[2] += 1;
+                                cases.push_str("      <skipped/>\n");
+                            }
+                            _ => {}
+                        }
+                        let artifacts = scenario
+                            .steps
+                            .iter()
+                            .flat_map(|step| &step.attachments)
+                            .map(|path| format!("{}\n", path.display()))
+                            .collect::<String>();
+                        if !artifacts.is_empty() {
+                            _ = writeln!(cases, "      <system-out>{}</system-out>", escape(&artifacts));
+                        }
+                        cases.push_str("    </testcase>\n");
+                    }
+                    _ = writeln!(
+                        suites,
+                        "  <testsuite name=\"{suite_name}\" tests=\"{}\" failures=\"{}\" errors=\"0\" \
+                        skipped=\"{}\" time=\"{:.3}\">\n    <properties>\n{properties}    </properties>\n\
+                        {cases}  </testsuite>",
+                        counts[0],
+                        counts[1],
+                        counts[2],
+                        time.as_secs_f64(),
+                    );
+                    for (total, count) in totals.iter_mut().zip(counts) {
+                        *total += count;
+                    }
+                    total_time += time;
+                }
+                format!(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"{}\" tests=\"{}\" \
+                    failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{:.3}\">\n{suites}</testsuites>\n",
+                    escape(&browser),
+                    totals[0],
+                    totals[1],
+                    totals[2],
+                    total_time.as_secs_f64(),
+                )
+            }
+        }
+    }
+}
+
+pub(crate) fn build_junit_report_world_fns() -> TokenStream {
+    quote! {
+        // Characters not allowed in XML, like the ones of ANSI colors, are dropped.
+        fn __xml_escape(value: &str) -> String {
+            Self::__html_escape(
+                &value
+                    .chars()
+                    .filter(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+                    .collect::<String>(),
+            )
+        }
+
+        fn __discover_junit_report() -> Option<std::path::PathBuf> {
+            Self::__report_path("JUNIT_REPORT")
+        }
+    }
+}
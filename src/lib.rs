@@ -13,13 +13,68 @@
 //! parametrize them.
 //!
 //! - `BROWSER`: browser to use. Supported are `firefox`, `chrome`, and `edge`.
+//!   Pass a comma separated list like `chrome,firefox,edge` to run the features
+//!   once per browser (see [Browser matrix](#browser-matrix)).
 //! - `HEADLESS`: by default, tests are executed in headless mode. Set this
-//!   to `false` to run them in a visible browser.
+//!   to `false` to run them in a visible browser. Accepts `true`, `false`, `1`,
+//!   `0`, `yes` and `no`, case insensitive.
 //! - `WINDOW_SIZE`: size of the browser window. The default is `1920x1080`.
+//!   Append `@DPR` to emulate a device pixel ratio, like `390x844@3`.
 //! - `HOST_URL`: base URL of the application under test. The default is
 //!   `http://localhost:8080`.
+//! - `HOST_URL_<NAME>`: base URLs of other services of the application under
+//!   test, like `HOST_URL_ADMIN`, accessible by their lowercased name.
 //! - `DRIVER_URL`: the URL of the `WebDriver` server. The default is
 //!   `http://localhost:4444`.
+//! - `READY_TIMEOUT`: maximum time to wait for pages to be ready when navigating
+//!   with `goto_path_and_wait`, like `500ms`, `10s` or `2m`. The default is
+//!   `10s`.
+//! - `EXPECT_TIMEOUT`: maximum time to retry expectations created with `expect`
+//!   until they're met, like `500ms`, `10s` or `2m`. The default is `5s`.
+//! - `PAGE_LOAD_TIMEOUT`, `SCRIPT_TIMEOUT` and `IMPLICIT_WAIT`: `WebDriver`
+//!   timeouts of the session, like `500ms`, `10s` or `2m`. The defaults are
+//!   `300s`, `30s` and `0s`.
+//! - `PAGE_LOAD_STRATEGY`: when navigation methods return. Supported are `normal`
+//!   (the default, after the page has loaded), `eager` (after the DOM has been
+//!   parsed) and `none` (immediately).
+//! - `ARTIFACTS_DIR`: directory where the artifacts of the failed steps are
+//!   saved. The default is `target/artifacts`.
+//! - `SCREENSHOT_ON_FAILURE`: screenshots taken when a step fails. Supported are
+//!   `full` (the default, the whole page), `viewport`, `both` and `off`.
+//! - `SNAPSHOT_ON_FAILURE`: set to `false` to not save snapshots of the DOM when a
+//!   step fails. The default is `true`.
+//! - `SNAPSHOT_MAX_SIZE`: maximum size of each file of the snapshots of the DOM,
+//!   like `500KB` or `2MB`. The default is `5MB`.
+//! - `CONSOLE_ERRORS`: what to do when errors are logged in the console of the
+//!   browser during a scenario. Supported are `fail`, `warn` (the default) and
+//!   `ignore`.
+//! - `JSON_REPORT`: path of a report in the Cucumber JSON format to write at the
+//!   end of the run, with the artifacts of the steps embedded. Disabled by
+//!   default.
+//! - `HTML_REPORT`: path of a self-contained HTML report to write at the end of
+//!   the run. Disabled by default.
+//! - `JUNIT_REPORT`: path of a report in the `JUnit` XML format to write at the
+//!   end of the run. Disabled by default.
+//! - `ALLURE_RESULTS`: directory where the results of the scenarios are written
+//!   for Allure during the run. Disabled by default.
+//! - `TRACE`: when to save a trace of the scenarios with a screenshot, the URL and
+//!   the `WebDriver` commands of every step. Supported are `off` (the default),
+//!   `on-failure` and `always`.
+//! - `SCREENCAST`: when to save a screencast of the scenarios as an animated PNG.
+//!   Supported are `off` (the default), `on-failure` and `always`.
+//! - `SCREENCAST_FPS`: number of screenshots taken per second for the
+//!   screencasts, up to 30. Defaults to `2`.
+//! - `COMMAND_LOG`: whether to log the `WebDriver` commands of the worlds.
+//!   Supported are `off` (the default), `on` and `file`, which also writes the
+//!   commands of every scenario to a file. Requires the `tracing` argument of the
+//!   [`#[worlder]`][worlder] macro.
+//! - `WORLDER_LOG`: messages of the world written to the standard error, unless
+//!   the `tracing` argument of the [`#[worlder]`][worlder] macro is passed.
+//!   Supported are `warn` (the default, only problems), `info` (also the paths of
+//!   the artifacts and reports saved) and `off`.
+//! - `WORLDER_ENV_FILE`: path to a dotenv file to load the variables above from.
+//!   Overrides the `env_file` argument of the [`#[worlder]`][worlder] macro.
+//!   Variables already defined in the environment are never overridden.
 //!
 //! # Usage
 //!
@@ -44,17 +99,16 @@
 //!
 //! > See the reference of the created world [here][appworld-reference].
 //!
-//! Then, create a crate for tests and run the world as you would do with
-//! [`cucumber::World`] directly.
+//! Then, create a crate for tests and run the world with its `runner` function,
+//! which returns the runner of [`cucumber::World`] used to save failure artifacts.
 //!
 //! ```rust,ignore
 //! // tests/desktop.rs
 //! use your_crate::AppWorld;
-//! use cucumber::World;
 //!
 //! #[tokio::main]
 //! async fn main() {
-//!     AppWorld::cucumber()
+//!     AppWorld::runner()
 //!         .fail_on_skipped()
 //!         .run_and_exit("./features/desktop")
 //!         .await
@@ -78,6 +132,207 @@
 //! Where `desktop` is the name of your test file and `your-crate` is the name of
 //! the crate that contains the [`AppWorld`][appworld-reference] struct.
 //!
+//! ## Diagnosing the environment
+//!
+//! When a run misbehaves, the `doctor_and_exit` method of the world shows the
+//! configuration resolved from the environment, checks that the `WebDriver`
+//! server and the application under test are reachable, reports the versions of
+//! the driver and the browser, and gives hints to fix the problems found. Add a
+//! test target without harness to your crate to run it.
+//!
+//! ```toml
+//! [[test]]
+//! name = "doctor"
+//! harness = false
+//! ```
+//!
+//! ```rust,ignore
+//! // tests/doctor.rs
+//! use your_crate::AppWorld;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     AppWorld::doctor_and_exit().await
+//! }
+//! ```
+//!
+//! ```sh
+//! BROWSER=chrome cargo test --package your-crate --test doctor
+//! ```
+//!
+//! ## Browser matrix
+//!
+//! To run the same features against multiple browsers in one invocation, use
+//! `run_browser_matrix_and_exit` instead of running the world directly.
+//!
+//! ```rust,ignore
+//! // tests/desktop.rs
+//! use your_crate::AppWorld;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     AppWorld::run_browser_matrix_and_exit("./features/desktop").await
+//! }
+//! ```
+//!
+//! ```sh
+//! BROWSER=chrome,firefox cargo test --package your-crate --test desktop
+//! ```
+//!
+//! Each browser runs every scenario, its output is preceded by the browser name
+//! and a summary of passed, skipped and failed steps per browser is shown at the
+//! end. Scenarios of Firefox are executed one at a time, so passing
+//! `--concurrency=1` is not needed. Each browser requires its own webdriver
+//! server, so all of them must be reachable at `DRIVER_URL`, for example
+//! through a [Selenium Grid]. Only one browser matrix can run at a time in a
+//! process.
+//!
+//! To configure the runner of each browser, like filtering scenarios, setting
+//! retries or replacing the writer, use `run_browser_matrix_with`:
+//!
+//! ```rust,ignore
+//! AppWorld::run_browser_matrix_with("./features/desktop", |_browser, cucumber| {
+//!     cucumber.fail_on_skipped().retries(1)
+//! })
+//! .await;
+//! ```
+//!
+//! ## Failure artifacts
+//!
+//! When a step fails, the world saves a screenshot of the whole page to
+//! `ARTIFACTS_DIR/<feature>/<scenario>/<step>.png`, replacing the characters of
+//! the names that are not valid in paths by underscores, and appending the line
+//! of their row to the scenarios of the outlines, like `<scenario>-12`. Set
+//! `SCREENSHOT_ON_FAILURE=both` to also save the viewport to
+//! `<step>.viewport.png`. Alongside, the page source is saved to `<step>.html`
+//! and the URL, the window size, the cookies and the contents of the storages
+//! of the page to `<step>.json`. Sensitive values can be redacted from them
+//! with hooks added by `AppWorld::redact_snapshots_with`. The artifacts are
+//! saved by the runner returned by `AppWorld::runner()`, which is the one of
+//! `cucumber::World::cucumber` with a writer that saves them, so no other code
+//! is needed. When running a browser matrix, the artifacts of each browser are
+//! saved in a subdirectory named after it.
+//!
+//! ## Console errors
+//!
+//! The messages logged in the console of the browser, the uncaught errors and
+//! the unhandled rejections are collected during each scenario and returned by
+//! `AppWorld::console_messages`. Chrome and Edge report them through their
+//! logging preferences, while in Firefox they are recorded by a listener
+//! injected after every navigation of the world, so the messages logged while
+//! the page loads are missed. When a scenario fails or logs errors, its
+//! messages are saved to `ARTIFACTS_DIR/<feature>/<scenario>/console.log`.
+//! With `CONSOLE_ERRORS=fail` the scenarios logging errors fail, and with the
+//! default `warn` the errors are only reported. Known noise can be allowed with
+//! JavaScript regular expressions:
+//!
+//! ```rust,ignore
+//! AppWorld::allow_console_error("ResizeObserver loop");
+//! ```
+//!//!
+//! The policy is applied by an `after` hook of the runner, which setting another
+//! one with `after` would replace, so add the hooks to run after each scenario
+//! with `AppWorld::after_scenario_with` instead.
+//!
+//! ## Reports
+//!
+//! The writer of the runner returned by `AppWorld::runner()` collects a report
+//! of the run with the status and the duration of every step, and the artifacts
+//! saved for them. It's available with the `report` method of the writer
+//! returned by the run, and written at the end of the run to the files enabled
+//! by the environment variables:
+//!
+//! - `JSON_REPORT=target/cucumber.json` writes the Cucumber JSON format read by
+//!   most report viewers, with the screenshots, page sources and console logs
+//!   embedded in the steps they belong to.
+//! - `HTML_REPORT=target/report.html` writes a single HTML page with the
+//!   configuration of the world, the features, scenarios and steps with their
+//!   status and duration, and the artifacts embedded, filterable by status and
+//!   tag.
+//! - `JUNIT_REPORT=target/junit.xml` writes the `JUnit` XML format read by most CI
+//!   servers, with the test suites prefixed by the browser, the configuration
+//!   of the world as properties and the paths of the artifacts in the output of
+//!   the test cases.
+//! - `ALLURE_RESULTS=target/allure-results` writes a result per scenario as it
+//!   finishes, with its steps, tags as labels, the values of the examples of
+//!   scenario outlines as parameters and the artifacts as attachments, and an
+//!   `environment.properties` file with the configuration of the world. The
+//!   browser is added as the parent suite instead of to the name of the
+//!   directory, so the results of a browser matrix are shown together.
+//!
+//! When running a browser matrix, the name of the browser is added before the
+//! extension of the reports, like `target/cucumber.chrome.json`.
+//!
+//! ## Traces
+//!
+//! Set `TRACE=on-failure` to save a trace of the failed scenarios to
+//! `ARTIFACTS_DIR/<feature>/<scenario>/trace.zip`, or `TRACE=always` for all of
+//! them. After each step, a screenshot and the URL of the page are taken by the
+//! runner of the world. When the `tracing` argument of the
+//! [`#[worlder]`][worlder] macro is passed, the `WebDriver` commands sent by
+//! `thirtyfour` are recorded too with their durations, from its debug events on
+//! the thread that creates the runner, where `tracing::Span::current()` is then
+//! unknown. Extract the archive and open its `index.html` to scrub through the
+//! timeline of the scenario. The payloads of the commands are redacted by the
+//! hooks added with `AppWorld::redact_snapshots_with`.
+//!
+//! ## Screencasts
+//!
+//! `WebDriver` can't record videos of the browsers, so set `SCREENCAST=on-failure`
+//! to take screenshots in the background during the scenarios and save those of
+//! the failed ones to `ARTIFACTS_DIR/<feature>/<scenario>/screencast.png`, or
+//! `SCREENCAST=always` for all of them. It's an animated PNG, played by browsers
+//! and most image viewers, taking `SCREENCAST_FPS` screenshots per second.
+//! Screenshots identical to the previous one are skipped, and those of another
+//! size than the first one, like after resizing the window, are dropped. At most
+//! 120 frames are kept in memory, halving the frame rate each time a screencast
+//! reaches them.
+//!
+//! ## Command logs
+//!
+//! Set `COMMAND_LOG=on` to log every `WebDriver` command sent by `thirtyfour` in
+//! the runner of the world with its method, endpoint, payload, duration and
+//! outcome, which tells which command a hanging step is waiting for. The commands
+//! are observed from the events of `thirtyfour`, so the `tracing` crate must be
+//! passed to the macro, like `#[worlder(tracing = tracing)]`. They're emitted as
+//! [`tracing`] events inside `scenario` and `step` spans, at the `debug` level when
+//! sent, and at the `info` level when they finish, or `warn` when they fail.
+//!
+//! With `COMMAND_LOG=file`, the commands of each scenario are also written as
+//! JSON lines to `ARTIFACTS_DIR/<feature>/<scenario>/commands.jsonl` while they
+//! run, and attached to the reports. The payloads are truncated and redacted by
+//! the hooks added with `AppWorld::redact_snapshots_with`.
+//!
+//! ## Page objects
+//!
+//! Describe the pages of the application with the [`#[derive(Page)]`][page]
+//! macro to share their paths and locators between steps. The fields located by
+//! `role`, `label`, `placeholder` or `text` are `<World>Locator`s, which are also
+//! accepted by the `locate` and `expect` methods of the world.
+//!
+//! ```rust,ignore
+//! use cucumber_thirtyfour_worlder::Page;
+//! use thirtyfour::By;
+//!
+//! #[derive(Page)]
+//! #[page(path = "/login", world = AppWorld)]
+//! pub struct LoginPage {
+//!     #[locator(css = "input[type=email]")]
+//!     email: By,
+//!     #[locator(test_id = "submit")]
+//!     submit: By,
+//!     #[locator(role = "alert")]
+//!     error: AppWorldLocator,
+//! }
+//!
+//! #[when("I log in")]
+//! async fn log_in(world: &mut AppWorld) {
+//!     let page = LoginPage::open(world).await.unwrap();
+//!     page.email(world).await.unwrap().send_keys("me@example.com").await.unwrap();
+//!     page.submit(world).await.unwrap().click().await.unwrap();
+//! }
+//! ```
+//!
 //! # Known issues
 //!
 //! ## Additional configuration for cargo-machete
@@ -93,16 +348,38 @@
 //!
 //! [cucumber-rs]: https://cucumber-rs.github.io/cucumber/main/
 //! [thirtyfour]: https://docs.rs/thirtyfour/latest/thirtyfour/
+//! [`tracing`]: https://docs.rs/tracing/latest/tracing/
 //! [`cucumber::World`]: https://docs.rs/cucumber/latest/cucumber/trait.World.html
 //! [appworld-reference]: https://docs.rs/cucumber-thirtyfour-worlder-docref/latest/cucumber_thirtyfour_worlder_docref/struct.AppWorld.html
 //! [worlder]: https://docs.rs/cucumber-thirtyfour-worlder/latest/cucumber_thirtyfour_worlder/attr.worlder.html
+//! [page]: https://docs.rs/cucumber-thirtyfour-worlder/latest/cucumber_thirtyfour_worlder/derive.Page.html
 //! [cargo-machete]: https://github.com/bnjbvr/cargo-machete
+//! [Selenium Grid]: https://www.selenium.dev/documentation/grid/
 
+mod allure_report;
+mod artifacts;
+mod command_log;
+mod console;
+mod doctor;
+mod encoding;
+mod expect;
+mod frames;
+mod html_report;
+mod junit_report;
+mod locators;
+mod logging;
+mod navigation;
+mod page;
+mod report;
+mod screencast;
 #[cfg(test)]
 mod tests;
+mod timeouts;
+mod trace;
+mod wait;
 
 use proc_macro2::{TokenStream, TokenTree};
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::{
     parse::{Parse, ParseStream},
     parse_macro_input,
@@ -117,6 +394,44 @@ use syn::{
 ///   to `1` invoking cucumber tests when using Firefox. Multiple sessions in parallel
 ///   are not allowed by geckodriver and this limitation is easy to forget, hence this
 ///   convenient argument.
+/// - `env_file` (*str*, default none): path to a dotenv file from which the
+///   environment variables of the world are loaded, like `".env.e2e"`. Variables
+///   already defined in the environment take precedence and a missing file is
+///   ignored. The `WORLDER_ENV_FILE` environment variable overrides this path.
+/// - `hydration` (*str*, default none): when defined, navigation methods like
+///   `goto_path` wait for the WASM module of the page to hydrate it before
+///   returning, so steps don't interact with the page too early, and return a
+///   timeout error if it doesn't before `READY_TIMEOUT`. Accepts the presets
+///   `"wasm"`, which works for any framework, `"trunk"`, `"leptos"`, `"yew"` and
+///   `"dioxus"`, or a custom JavaScript expression that evaluates to `true` once
+///   the page is hydrated.
+/// - `test_id_attribute` (*str*, default `"data-testid"`): attribute used to tag
+///   elements with test ids, located by the `by_test_id`, `find_by_test_id` and
+///   `find_all_by_test_id` methods of the world.
+/// - `tracing` (*path*, default none): path of the `tracing` crate, like `tracing`,
+///   used to emit the messages of the world, like the paths of the artifacts saved
+///   or the errors saving them, as `tracing` events. Otherwise, they're written to
+///   the standard error according to the `WORLDER_LOG` environment variable. Also
+///   used to record the `WebDriver` commands for the traces, and to log them inside
+///   spans of their scenario and step when the `COMMAND_LOG` environment variable
+///   is enabled.
+///
+/// Besides the world, the macro creates the next types, prefixed with the name of
+/// the world:
+///
+/// - `<World>Readiness`: conditions to wait for pages to be ready after
+///   navigating to them with `goto_path_and_wait`.
+/// - `<World>Locator`: locators of elements by ARIA role, label, placeholder or
+///   text, besides the ones of `thirtyfour::By`, used by the `locate` and
+///   `expect` methods of the world.
+/// - `<World>Expect`: auto-retrying expectations about elements, created with
+///   the `expect` method of the world.
+/// - `<World>Writer`: cucumber writer used by the `runner` function of the
+///   world to save the artifacts of the failed steps.
+/// - `<World>Report`, `<World>ReportFeature`, `<World>ReportScenario` and
+///   `<World>ReportStep`: report of a run collected by the writer.
+/// - `<World>Command`: `WebDriver` command recorded when tracing the scenarios or
+///   logging the commands.
 ///
 /// See the reference of the created world [here][appworld-reference].
 ///
@@ -136,7 +451,16 @@ pub fn worlder(
     let (check_concurrency_cli_option_when_firefox, check_concurrency_cli_option_when_firefox_fn) =
         if args.check_concurrency_cli_option_when_firefox {
             (
-                quote!(Self::__check_firefox_concurrency_cli_option()),
+                quote! {
+                    // The browser matrix limits the concurrency of Firefox by itself.
+                    if Self::__matrix_browser()
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner)
+                        .is_none()
+                    {
+                        Self::__check_firefox_concurrency_cli_option();
+                    }
+                },
                 build_check_concurrency_cli_option_when_firefox_fn(),
             )
         } else {
@@ -144,6 +468,14 @@ pub fn worlder(
         };
     let cucumber = args.cucumber;
     let thirtyfour = args.thirtyfour;
+    let env_file = args
+        .env_file
+        .map_or_else(|| quote!(None), |path| quote!(Some(#path)));
+    let doctor_fns = doctor::build_doctor_fns(&thirtyfour);
+    let encoding_fns = encoding::build_encoding_fns();
+    let frames_fns = frames::build_frames_fns(&thirtyfour);
+    let navigation_fns = navigation::build_navigation_fns(&thirtyfour);
+    let timeouts_fns = timeouts::build_timeouts_fns(&thirtyfour);
 
     let mut before_struct = TokenStream::new();
     let original_struct = TokenStream::from(stream.clone());
@@ -237,22 +569,110 @@ pub fn worlder(
         )
     };
 
+    let readiness_ident = format_ident!("{}Readiness", struct_name_ident.to_string());
+    let readiness_enum =
+        wait::build_readiness_enum(&vis_ident, &struct_name_ident, &readiness_ident);
+    let locator_ident = format_ident!("{}Locator", struct_name_ident.to_string());
+    let locator_enum = locators::build_locator_enum(&thirtyfour, &vis_ident, &locator_ident);
+    let locators_fns =
+        locators::build_locators_fns(&thirtyfour, &locator_ident, &args.test_id_attribute.value());
+    let expect_ident = format_ident!("{}Expect", struct_name_ident.to_string());
+    let expect_struct = expect::build_expect_struct(
+        &thirtyfour,
+        &vis_ident,
+        &struct_name_ident,
+        &expect_ident,
+        &locator_ident,
+    );
+    let expect_fns = expect::build_expect_fns(&expect_ident, &locator_ident);
+    let wait_fns = wait::build_wait_fns(&thirtyfour, &readiness_ident, args.hydration.as_ref());
+    let writer_ident = format_ident!("{}Writer", struct_name_ident.to_string());
+    let report_ident = format_ident!("{}Report", struct_name_ident.to_string());
+    let writer_struct = artifacts::build_writer_struct(
+        &cucumber,
+        &vis_ident,
+        &struct_name_ident,
+        &writer_ident,
+        &report_ident,
+    );
+    let report_structs =
+        report::build_report_structs(&cucumber, &vis_ident, &struct_name_ident, &report_ident);
+    let report_fns = report::build_report_fns();
+    let html_report_structs = html_report::build_html_report_fns(&struct_name_ident, &report_ident);
+    let html_report_fns = html_report::build_html_report_world_fns();
+    let junit_report_structs =
+        junit_report::build_junit_report_fns(&struct_name_ident, &report_ident);
+    let junit_report_fns = junit_report::build_junit_report_world_fns();
+    let allure_report_structs =
+        allure_report::build_allure_report_fns(&struct_name_ident, &report_ident);
+    let allure_report_fns = allure_report::build_allure_report_world_fns();
+    let artifacts_fns =
+        artifacts::build_artifacts_fns(&cucumber, &thirtyfour, &struct_name_ident, &writer_ident);
+    let logging_fns = logging::build_logging_fns(args.tracing.as_ref());
+    let console_fns = console::build_console_fns(&thirtyfour);
+    let command_ident = format_ident!("{}Command", struct_name_ident.to_string());
+    let command_struct = trace::build_command_struct(&vis_ident, &command_ident);
+    let trace_fns = trace::build_trace_fns(
+        &thirtyfour,
+        args.tracing.as_ref(),
+        &struct_name_ident,
+        &command_ident,
+        &report_ident,
+    );
+    let screencast_fns = screencast::build_screencast_fns(&cucumber, &thirtyfour);
+    let command_log_fns = command_log::build_command_log_fns(
+        &cucumber,
+        args.tracing.as_ref(),
+        &command_ident,
+        &report_ident,
+    );
+
     let ret = quote! {
         #before_struct
         #[derive(Debug, #cucumber::World)]
         #[world(init = Self::new)]
         #vis_ident #struct_ident #struct_name_ident {
             driver: #thirtyfour::WebDriver,
+            browser: String,
             driver_url: String,
             host_url: String,
+            hosts: std::collections::BTreeMap<String, String>,
+            allowed_hosts: Vec<String>,
             headless: bool,
             window_size: (u32, u32),
+            device_pixel_ratio: f64,
+            ready_timeout: std::time::Duration,
+            page_load_timeout: std::time::Duration,
+            script_timeout: std::time::Duration,
+            implicit_wait: std::time::Duration,
+            page_load_strategy: String,
+            expect_timeout: std::time::Duration,
+            artifacts_dir: std::path::PathBuf,
+            screenshot_on_failure: String,
+            snapshot_on_failure: bool,
+            snapshot_max_size: usize,
+            console_errors: String,
+            console_messages: std::sync::Mutex<Vec<(String, String)>>,
+            trace: String,
+            screencast: String,
+            screencast_fps: f64,
+            command_log: String,
         }
 
+        #readiness_enum
+        #locator_enum
+        #expect_struct
+        #writer_struct
+        #report_structs
+        #html_report_structs
+        #junit_report_structs
+        #allure_report_structs
+        #command_struct
+
         impl #struct_name_ident {
             #[doc(hidden)]
             pub async fn new() -> Self {
-                Self::__build_driver().await
+                Self::__build_driver().await.unwrap_or_else(|err| panic!("{err}"))
             }
 
             #[doc = "Get the driver of the world."]
@@ -261,6 +681,14 @@ pub fn worlder(
                 &self.driver
             }
 
+            #[doc = "Get the browser of the world."]
+            #[doc = ""]
+            #[doc = "It's defined by the `BROWSER` environment variable or, when running a browser matrix, by the browser currently being run."]
+            #[must_use]
+            pub fn browser(&self) -> &str {
+                &self.browser
+            }
+
             #[doc = "Get the driver URL of the world."]
             #[doc = ""]
             #[doc = "It's defined by the `DRIVER_URL` environment variable, which defaults to `\"http://localhost:4444\"`."]
@@ -277,6 +705,32 @@ pub fn worlder(
                 &self.host_url
             }
 
+            #[doc = "Get the URL of a named host of the world."]
+            #[doc = ""]
+            #[doc = "Named hosts are defined by `HOST_URL_<NAME>` environment variables and accessed by"]
+            #[doc = "their lowercased name, so `HOST_URL_ADMIN` is accessed with `host_url_for(\"admin\")`."]
+            #[doc = ""]
+            #[doc = "# Panics"]
+            #[doc = ""]
+            #[doc = "If the host is not defined."]
+            #[must_use]
+            pub fn host_url_for(&self, name: &str) -> &str {
+                self.hosts
+                    .get(&name.to_ascii_lowercase())
+                    .unwrap_or_else(|| {
+                        panic!(
+                            "Host \"{name}\" is not defined. Define it with the \
+                            HOST_URL_{} environment variable. Defined hosts are: [{}].",
+                            name.to_ascii_uppercase(),
+                            self.hosts
+                                .keys()
+                                .map(|name| format!("\"{name}\""))
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                        )
+                    })
+            }
+
             #[doc = "Get the headless mode of the world."]
             #[doc = ""]
             #[doc = "It's defined by the `HEADLESS` environment variable, which defaults to `true`."]
@@ -293,25 +747,33 @@ pub fn worlder(
                 self.window_size
             }
 
-            #[doc = "Navigate to the given path inside the host."]
-            pub async fn goto_path(&self, path: &str) -> Result<&Self, #thirtyfour::error::WebDriverError> {
-                let url = format!("{}{}", self.host_url(), path);
-                if let Err(err) = self.driver().goto(&url).await {
-                    Err(err)
-                } else {
-                    Ok(self)
-                }
+            #[doc = "Get the device pixel ratio of the world."]
+            #[doc = ""]
+            #[doc = "It's defined by the `@DPR` suffix of the `WINDOW_SIZE` environment variable, which defaults to `1`."]
+            #[must_use]
+            pub fn device_pixel_ratio(&self) -> f64 {
+                self.device_pixel_ratio
             }
 
-            async fn __build_driver() -> Self {
-                let browser = Self::__discover_browser();
+            async fn __build_driver() -> Result<Self, String> {
+                let browser = Self::__discover_browser()?;
                 let driver_url = Self::__discover_driver_url();
                 let host_url = Self::__discover_host_url();
-                let headless = Self::__discover_headless();
-                let (window_width, window_height) = Self::__discover_window_size();
+                let hosts = Self::__discover_hosts();
+                let headless = Self::__discover_headless()?;
+                let (window_width, window_height, device_pixel_ratio) =
+                    Self::__discover_window_size()?;
+                let device_pixel_ratio_opt =
+                    format!("--force-device-scale-factor={device_pixel_ratio}");
+                let timeouts = Self::__discover_timeouts()?;
+                let (page_load_timeout, script_timeout, implicit_wait) = timeouts;
+                let page_load_strategy = Self::__discover_page_load_strategy()?;
+                Self::__discover_log_level()?;
 
                 let driver = if &browser == "chrome" {
                     let mut caps = #thirtyfour::DesiredCapabilities::chrome();
+                    Self::__set_session_capabilities(&mut caps, timeouts, &page_load_strategy)?;
+                    Self::__set_logging_capabilities(&mut caps, "goog:loggingPrefs")?;
                     let window_size_opt = format!(
                         "--window-size={window_width},{window_height}",
                     );
@@ -319,44 +781,61 @@ pub fn worlder(
                     if headless {
                         opts.push("--headless");
                     }
+                    if (device_pixel_ratio - 1.0).abs() > f64::EPSILON {
+                        opts.push(&device_pixel_ratio_opt);
+                    }
                     <#thirtyfour::ChromeCapabilities
                         as
                     #thirtyfour::BrowserCapabilitiesHelper>::insert_browser_option(
                         &mut caps, "args", opts
                     )
-                        .unwrap_or_else(|err| {
-                            panic!("Failed to set Chrome options: {err}");
-                        });
+                        .map_err(|err| format!("Failed to set Chrome options: {err}"))?;
                     #thirtyfour::WebDriver::new(&driver_url, caps)
                         .await
-                        .unwrap_or_else(|err| {
-                            panic!(
+                        .map_err(|err| {
+                            format!(
                                 "Failed to create WebDriver for Chrome: {err}. \
                                 Make sure that chromedriver server is running at {driver_url}",
                             )
-                        })
+                        })?
                 } else if &browser == "firefox" {
-                    #check_concurrency_cli_option_when_firefox;
+                    #check_concurrency_cli_option_when_firefox
                     let mut caps = #thirtyfour::DesiredCapabilities::firefox();
+                    Self::__set_session_capabilities(&mut caps, timeouts, &page_load_strategy)?;
                     if headless {
-                        caps.set_headless().unwrap_or_else(|err| {
-                            panic!("Failed to set Firefox headless mode: {err}");
-                        });
+                        caps.set_headless()
+                            .map_err(|err| format!("Failed to set Firefox headless mode: {err}"))?;
                     }
-                    let driver = #thirtyfour::WebDriver::new(&driver_url, caps).await.unwrap_or_else(|err| {
-                        panic!(
+                    if (device_pixel_ratio - 1.0).abs() > f64::EPSILON {
+                        let prefs = std::collections::HashMap::from([(
+                            "layout.css.devPixelsPerPx",
+                            device_pixel_ratio.to_string(),
+                        )]);
+                        <#thirtyfour::FirefoxCapabilities
+                            as
+                        #thirtyfour::BrowserCapabilitiesHelper>::insert_browser_option(
+                            &mut caps, "prefs", prefs
+                        )
+                            .map_err(|err| format!("Failed to set Firefox preferences: {err}"))?;
+                    }
+                    let driver = #thirtyfour::WebDriver::new(&driver_url, caps).await.map_err(|err| {
+                        format!(
                             "Failed to create WebDriver for Firefox: {err}. \
                             Make sure that geckodriver server is running at {driver_url}",
                         )
-                    });
+                    })?;
                     // Firefox loads the window dimensions of the last session,
                     // so we need to set the window size explicitly.
                     driver.set_window_rect(0, 0, window_width, window_height)
                         .await
-                        .expect("Failed to set window size to {width}x{height}");
+                        .map_err(|err| {
+                            format!("Failed to set window size to {window_width}x{window_height}: {err}")
+                        })?;
                     driver
                 } else if &browser == "edge" {
                     let mut caps = #thirtyfour::DesiredCapabilities::edge();
+                    Self::__set_session_capabilities(&mut caps, timeouts, &page_load_strategy)?;
+                    Self::__set_logging_capabilities(&mut caps, "ms:loggingPrefs")?;
                     let window_size_opt = format!(
                         "--window-size={window_width},{window_height}",
                     );
@@ -364,85 +843,431 @@ pub fn worlder(
                     if headless {
                         opts.push("--headless");
                     }
+                    if (device_pixel_ratio - 1.0).abs() > f64::EPSILON {
+                        opts.push(&device_pixel_ratio_opt);
+                    }
                     <#thirtyfour::EdgeCapabilities
                         as
                     #thirtyfour::BrowserCapabilitiesHelper>::insert_browser_option(&mut caps, "args", opts)
-                        .unwrap_or_else(|err| {
-                            panic!("Failed to set Edge options: {err}");
-                        });
-                    #thirtyfour::WebDriver::new(&driver_url, caps).await.unwrap_or_else(|err| {
-                        panic!(
+                        .map_err(|err| format!("Failed to set Edge options: {err}"))?;
+                    #thirtyfour::WebDriver::new(&driver_url, caps).await.map_err(|err| {
+                        format!(
                             "Failed to create WebDriver for Edge: {err}. \
                             Make sure that edgedriver server is running at {driver_url}",
                         )
-                    })
+                    })?
                 } else {
-                    panic!(
+                    return Err(format!(
                         "Unsupported browser. BROWSER environment variable is: \
                         {browser}. Supported browsers are: \"chrome\", \"firefox\" \
                         and \"edge\"."
-                    );
+                    ));
                 };
 
-                Self {
+                Ok(Self {
                     driver,
+                    browser,
                     driver_url,
                     host_url,
+                    hosts,
+                    allowed_hosts: vec![],
                     headless,
                     window_size: (window_width, window_height),
+                    device_pixel_ratio,
+                    ready_timeout: Self::__discover_ready_timeout()?,
+                    page_load_timeout,
+                    script_timeout,
+                    implicit_wait,
+                    page_load_strategy,
+                    expect_timeout: Self::__discover_expect_timeout()?,
+                    artifacts_dir: Self::__discover_artifacts_dir(),
+                    screenshot_on_failure: Self::__discover_screenshot_on_failure()?,
+                    snapshot_on_failure: Self::__discover_snapshot_on_failure()?,
+                    snapshot_max_size: Self::__discover_snapshot_max_size()?,
+                    console_errors: Self::__discover_console_errors()?,
+                    console_messages: std::sync::Mutex::new(vec![]),
+                    trace: Self::__discover_trace()?,
+                    screencast: Self::__discover_screencast()?,
+                    screencast_fps: Self::__discover_screencast_fps()?,
+                    command_log: Self::__discover_command_log()?,
+                })
+            }
+
+            #[doc = "Run the features once per browser defined in the `BROWSER` environment variable."]
+            #[doc = ""]
+            #[doc = "`BROWSER` accepts a comma separated list of browsers like `chrome,firefox,edge`."]
+            #[doc = "The output of each browser is preceded by its name and a summary of the"]
+            #[doc = "steps passed, skipped and failed by each browser is written at the end."]
+            #[doc = "Scenarios of Firefox are executed one at a time because geckodriver does not"]
+            #[doc = "allow multiple sessions in parallel."]
+            #[doc = ""]
+            #[doc = "The browser being run is global to the process, so only one browser matrix can"]
+            #[doc = "run at a time and running another one concurrently panics."]
+            #[doc = ""]
+            #[doc = "Returns `true` if the execution has not failed for any browser."]
+            pub async fn run_browser_matrix<I>(input: I) -> bool
+            where
+                I: AsRef<std::path::Path> + Clone,
+            {
+                Self::run_browser_matrix_with(input, |_, cucumber| cucumber).await
+            }
+
+            #[doc = "Run the features once per browser like [`run_browser_matrix`](Self::run_browser_matrix),"]
+            #[doc = "configuring the runner of each browser with a closure."]
+            #[doc = ""]
+            #[doc = "The closure receives the name of the browser and the runner returned by"]
+            #[doc = "[`runner`](Self::runner), already limited to one scenario at a time for"]
+            #[doc = "Firefox, and returns the runner to use, so filters, retries, writers or"]
+            #[doc = "`fail_on_skipped` can be set for every browser:"]
+            #[doc = ""]
+            #[doc = "```rust,ignore"]
+            #[doc = "AppWorld::run_browser_matrix_with(\"./features\", |browser, cucumber| {"]
+            #[doc = "    let cucumber = cucumber.fail_on_skipped().retries(1);"]
+            #[doc = "    if browser == \"firefox\" {"]
+            #[doc = "        cucumber.which_scenario(|_, _, scenario| {"]
+            #[doc = "            if scenario.tags.iter().any(|tag| tag == \"firefox-only\") {"]
+            #[doc = "                ScenarioType::Serial"]
+            #[doc = "            } else {"]
+            #[doc = "                ScenarioType::Concurrent"]
+            #[doc = "            }"]
+            #[doc = "        })"]
+            #[doc = "    } else {"]
+            #[doc = "        cucumber"]
+            #[doc = "    }"]
+            #[doc = "})"]
+            #[doc = ".await"]
+            #[doc = "```"]
+            #[doc = ""]
+            #[doc = "Returns `true` if the execution has not failed for any browser."]
+            pub async fn run_browser_matrix_with<I, P, R, Wr, Cli, F>(input: I, mut configure: F) -> bool
+            where
+                I: AsRef<std::path::Path> + Clone,
+                P: #cucumber::Parser<I>,
+                R: #cucumber::Runner<Self>,
+                Wr: #cucumber::writer::Stats<Self> + #cucumber::writer::Normalized,
+                Cli: #cucumber::cli::Args,
+                F: FnMut(
+                    &str,
+                    #cucumber::Cucumber<
+                        Self,
+                        #cucumber::parser::Basic,
+                        I,
+                        #cucumber::runner::Basic<Self>,
+                        #writer_ident<#cucumber::writer::Summarize<#cucumber::writer::Normalize<Self, #cucumber::writer::Basic>>>,
+                    >,
+                ) -> #cucumber::Cucumber<Self, P, I, R, Wr, Cli>,
+            {
+                assert!(
+                    Self::__matrix_browser()
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner)
+                        .is_none(),
+                    "Another browser matrix is already running in this process",
+                );
+
+                let mut results = vec![];
+                for browser in Self::__discover_browsers().unwrap_or_else(|err| panic!("{err}")) {
+                    eprintln!("Running features with browser \"{browser}\"");
+                    *Self::__matrix_browser()
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(browser.clone());
+
+                    let cucumber = Self::runner::<I>();
+                    let cucumber = if &browser == "firefox" {
+                        cucumber.max_concurrent_scenarios(1)
+                    } else {
+                        cucumber
+                    };
+                    let writer = configure(&browser, cucumber).run(input.clone()).await;
+                    results.push((
+                        browser,
+                        #cucumber::writer::Stats::<Self>::passed_steps(&writer),
+                        #cucumber::writer::Stats::<Self>::skipped_steps(&writer),
+                        #cucumber::writer::Stats::<Self>::failed_steps(&writer),
+                        #cucumber::writer::Stats::<Self>::execution_has_failed(&writer),
+                    ));
                 }
+                *Self::__matrix_browser()
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner) = None;
+
+                eprintln!("Browser matrix summary:");
+                for (browser, passed, skipped, failed, _) in &results {
+                    eprintln!(
+                        "  {browser}: {passed} passed, {skipped} skipped, {failed} failed steps",
+                    );
+                }
+                !results.iter().any(|(.., has_failed)| *has_failed)
             }
 
-            fn __discover_browser() -> String {
-                std::env::var("BROWSER").unwrap_or_else(|_| {
-                    panic!(
-                        "BROWSER environment variable is not set. \
-                         Supported browsers are: \"chrome\", \"firefox\" \
-                         and \"edge\"."
-                    )
-                })
+            #[doc = "Run the features once per browser defined in the `BROWSER` environment variable"]
+            #[doc = "and exit with a non-zero code if the execution has failed for any of them."]
+            #[doc = ""]
+            #[doc = "See [`run_browser_matrix`](Self::run_browser_matrix) for details."]
+            pub async fn run_browser_matrix_and_exit<I>(input: I)
+            where
+                I: AsRef<std::path::Path> + Clone,
+            {
+                if !Self::run_browser_matrix(input).await {
+                    std::process::exit(1);
+                }
+            }
+
+            fn __matrix_browser() -> &'static std::sync::Mutex<Option<String>> {
+                static MATRIX_BROWSER: std::sync::Mutex<Option<String>> = std::sync::Mutex::new(None);
+                &MATRIX_BROWSER
+            }
+
+            fn __discover_browser() -> Result<String, String> {
+                if let Some(browser) = Self::__matrix_browser()
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .clone()
+                {
+                    return Ok(browser);
+                }
+
+                let mut browsers = Self::__discover_browsers()?;
+                if browsers.len() != 1 {
+                    return Err(format!(
+                        "BROWSER environment variable defines multiple browsers: {}. \
+                         Run them with `run_browser_matrix` or `run_browser_matrix_and_exit`.",
+                        browsers.join(", "),
+                    ));
+                }
+                Ok(browsers.remove(0))
+            }
+
+            fn __discover_browsers() -> Result<Vec<String>, String> {
+                Self::__env_file().as_ref().map_err(Clone::clone)?;
+                let value = Self::__config_value("BROWSER").ok_or_else(|| {
+                    "BROWSER environment variable is not set. \
+                     Supported browsers are: \"chrome\", \"firefox\" \
+                     and \"edge\"."
+                        .to_string()
+                })?;
+                let browsers = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|browser| !browser.is_empty())
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>();
+                if browsers.is_empty() {
+                    return Err("BROWSER environment variable is empty. \
+                        Supported browsers are: \"chrome\", \"firefox\" \
+                        and \"edge\"."
+                        .to_string());
+                }
+                Ok(browsers)
             }
 
             fn __discover_driver_url() -> String {
-                std::env::var("DRIVER_URL").unwrap_or("http://localhost:4444".to_string())
+                Self::__config_value("DRIVER_URL").unwrap_or("http://localhost:4444".to_string())
             }
 
             fn __discover_host_url() -> String {
-                std::env::var("HOST_URL").unwrap_or("http://localhost:8080".to_string())
+                Self::__config_value("HOST_URL").unwrap_or("http://localhost:8080".to_string())
             }
 
-            fn __discover_headless() -> bool {
-                std::env::var("HEADLESS").unwrap_or("true".to_string()) == "true"
+            fn __discover_hosts() -> std::collections::BTreeMap<String, String> {
+                let file_variables = Self::__env_file()
+                    .iter()
+                    .flatten()
+                    .flat_map(|(_, variables)| variables.keys().cloned());
+                // Variables that are not valid UTF-8 can't define hosts, and `std::env::vars`
+                // panics on them.
+                std::env::vars_os()
+                    .filter_map(|(name, _)| name.into_string().ok())
+                    .chain(file_variables)
+                    .filter_map(|variable| {
+                        let name = variable.strip_prefix("HOST_URL_")?;
+                        if name.is_empty() {
+                            return None;
+                        }
+                        Some((name.to_ascii_lowercase(), Self::__config_value(&variable)?))
+                    })
+                    .collect()
             }
 
-            fn __discover_window_size() -> (u32, u32) {
-                let window_size = std::env::var("WINDOW_SIZE").unwrap_or("1920x1080".to_string());
-                let mut parts = window_size.split('x');
-                let width = parts.next().unwrap_or_else(|| {
-                    panic!(
-                        "Invalid WINDOW_SIZE environment variable format. \
-                        Expected format: WIDTHxHEIGHT"
-                    );
-                }).parse::<u32>().unwrap_or_else(|_| {
-                    panic!(
-                        "Invalid WINDOW_SIZE environment variable format. \
-                        Expected format: WIDTHxHEIGHT"
-                    );
-                });
-                let height = parts.next().unwrap_or_else(|| {
-                    panic!(
-                        "Invalid WINDOW_SIZE environment variable format. \
-                        Expected format: WIDTHxHEIGHT"
-                    );
-                }).parse::<u32>().unwrap_or_else(|_| {
-                    panic!(
-                        "Invalid WINDOW_SIZE environment variable format. \
-                        Expected format: WIDTHxHEIGHT"
-                    );
-                });
-                (width, height)
+            fn __discover_headless() -> Result<bool, String> {
+                Self::__config_value("HEADLESS")
+                    .map_or(Ok(true), |value| Self::__parse_bool("HEADLESS", &value))
+            }
+
+            fn __discover_window_size() -> Result<(u32, u32, f64), String> {
+                Self::__config_value("WINDOW_SIZE").map_or(Ok((1920, 1080, 1.0)), |value| {
+                    Self::__parse_window_size("WINDOW_SIZE", &value)
+                })
+            }
+
+            fn __parse_bool(name: &str, value: &str) -> Result<bool, String> {
+                match value.trim().to_ascii_lowercase().as_str() {
+                    "true" | "1" | "yes" => Ok(true),
+                    "false" | "0" | "no" => Ok(false),
+                    _ => Err(format!(
+                        "Invalid {name} environment variable value \"{value}\". \
+                        Expected one of: \"true\", \"false\", \"1\", \"0\", \
+                        \"yes\" or \"no\"."
+                    )),
+                }
             }
 
+            fn __parse_window_size(name: &str, value: &str) -> Result<(u32, u32, f64), String> {
+                let invalid = |reason: &str| {
+                    format!(
+                        "Invalid {name} environment variable value \"{value}\": {reason}. \
+                        Expected format: WIDTHxHEIGHT or WIDTHxHEIGHT@DPR, \
+                        like \"1920x1080\" or \"390x844@3\"."
+                    )
+                };
+                let (size, device_pixel_ratio) = match value.trim().split_once('@') {
+                    Some((size, device_pixel_ratio)) => {
+                        let device_pixel_ratio = device_pixel_ratio
+                            .parse::<f64>()
+                            .map_err(|_| invalid("the device pixel ratio is not a number"))?;
+                        if !device_pixel_ratio.is_finite() || device_pixel_ratio <= 0.0 {
+                            return Err(invalid("the device pixel ratio must be greater than zero"));
+                        }
+                        (size, device_pixel_ratio)
+                    }
+                    None => (value.trim(), 1.0),
+                };
+                let parts = size.split('x').collect::<Vec<_>>();
+                let [width, height] = parts.as_slice() else {
+                    return Err(invalid("expected exactly two dimensions"));
+                };
+                let parse_dimension = |dimension: &str| match dimension.parse::<u32>() {
+                    Ok(0) => Err(invalid("dimensions must be greater than zero")),
+                    Ok(dimension) => Ok(dimension),
+                    Err(_) => Err(invalid("dimensions must be positive integers")),
+                };
+                Ok((parse_dimension(width)?, parse_dimension(height)?, device_pixel_ratio))
+            }
+
+            #[doc = "Get the path of the dotenv file from which the environment variables have been loaded."]
+            #[doc = ""]
+            #[doc = "It's defined by the `WORLDER_ENV_FILE` environment variable or the `env_file` argument of the `#[worlder]` macro."]
+            #[doc = "Returns `None` if no file has been loaded because it's missing or invalid."]
+            #[must_use]
+            pub fn env_file() -> Option<&'static std::path::Path> {
+                Self::__env_file()
+                    .as_ref()
+                    .ok()?
+                    .as_ref()
+                    .map(|(path, _)| path.as_path())
+            }
+
+            fn __env_file() -> &'static Result<
+                Option<(std::path::PathBuf, std::collections::HashMap<String, String>)>,
+                String,
+            > {
+                static ENV_FILE: std::sync::OnceLock<
+                    Result<
+                        Option<(std::path::PathBuf, std::collections::HashMap<String, String>)>,
+                        String,
+                    >,
+                > = std::sync::OnceLock::new();
+                ENV_FILE.get_or_init(|| {
+                    let env_file: Option<&str> = #env_file;
+                    let (path, required) = match std::env::var("WORLDER_ENV_FILE") {
+                        Ok(path) => (path, true),
+                        Err(_) => match env_file {
+                            Some(path) => (path.to_string(), false),
+                            None => return Ok(None),
+                        },
+                    };
+                    let content = match std::fs::read_to_string(&path) {
+                        Ok(content) => content,
+                        Err(err) if err.kind() == std::io::ErrorKind::NotFound && !required => {
+                            return Ok(None);
+                        }
+                        Err(err) => return Err(format!("Failed to read environment file {path}: {err}")),
+                    };
+
+                    let variables = Self::__parse_env_file(&path, &content)?;
+                    Ok(Some((std::path::PathBuf::from(path), variables)))
+                })
+            }
+
+            fn __parse_env_file(
+                path: &str,
+                content: &str,
+            ) -> Result<std::collections::HashMap<String, String>, String> {
+                let mut variables = std::collections::HashMap::new();
+                for line in content.lines() {
+                    let line = line.trim();
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    let line = line.strip_prefix("export ").unwrap_or(line);
+                    let Some((name, value)) = line.split_once('=') else {
+                        return Err(format!(
+                            "Invalid line in environment file {path}: \"{line}\". \
+                            Expected format: NAME=VALUE"
+                        ));
+                    };
+                    let value = value.trim();
+                    let value = if let Some(value) = value
+                        .strip_prefix('"')
+                        .and_then(|value| value.strip_suffix('"'))
+                    {
+                        value.replace("\\n", "\n").replace("\\\"", "\"")
+                    } else if let Some(value) = value
+                        .strip_prefix('\'')
+                        .and_then(|value| value.strip_suffix('\''))
+                    {
+                        value.to_string()
+                    } else {
+                        value
+                            .split_once(" #")
+                            .map_or(value, |(value, _)| value)
+                            .trim_end()
+                            .to_string()
+                    };
+                    variables.insert(name.trim().to_string(), value);
+                }
+                Ok(variables)
+            }
+
+            // An environment file that can't be loaded is reported by
+            // `__discover_browsers`, which is called first by every run.
+            fn __config_value(name: &str) -> Option<String> {
+                std::env::var(name).ok().or_else(|| match Self::__env_file() {
+                    Ok(Some((_, variables))) => variables.get(name).cloned(),
+                    Ok(None) | Err(_) => None,
+                })
+            }
+
+            fn __config_source(name: &str) -> String {
+                if std::env::var_os(name).is_some() {
+                    return "environment variable".to_string();
+                }
+                match Self::__env_file() {
+                    Ok(Some((path, variables))) if variables.contains_key(name) => {
+                        format!("environment file {}", path.display())
+                    }
+                    _ => "default".to_string(),
+                }
+            }
+
+            #navigation_fns
+            #wait_fns
+            #timeouts_fns
+            #expect_fns
+            #locators_fns
+            #frames_fns
+            #artifacts_fns
+            #logging_fns
+            #console_fns
+            #report_fns
+            #html_report_fns
+            #junit_report_fns
+            #allure_report_fns
+            #trace_fns
+            #screencast_fns
+            #command_log_fns
+            #doctor_fns
+            #encoding_fns
             #check_concurrency_cli_option_when_firefox_fn
         }
     };
@@ -499,8 +1324,56 @@ fn build_check_concurrency_cli_option_when_firefox_fn() -> TokenStream {
     }
 }
 
+/// Derive macro to build page objects for the worlds created with [`#[worlder]`][worlder].
+///
+/// Requires a `#[page]` attribute with the next named arguments:
+///
+/// - `path` (*str*): path of the page inside the host, like `"/login"`.
+/// - `world` (*path*): world struct created with [`#[worlder]`][worlder].
+/// - `thirtyfour` (*path*, default `::thirtyfour`): path to the `thirtyfour` crate.
+///
+/// Every field of the struct must be a `thirtyfour::By` annotated with a
+/// `#[locator]` attribute defining how the element is located, one of
+/// `css`, `xpath`, `id`, `name`, `tag`, `class_name`, `link_text` or `test_id`.
+///
+/// ```rust,ignore
+/// use cucumber_thirtyfour_worlder::Page;
+/// use thirtyfour::By;
+///
+/// #[derive(Page)]
+/// #[page(path = "/login", world = AppWorld)]
+/// pub struct LoginPage {
+///     #[locator(css = "input[type=email]")]
+///     email: By,
+///     #[locator(test_id = "submit")]
+///     submit: By,
+/// }
+/// ```
+///
+/// The macro implements [`Default`] for the page and generates:
+///
+/// - `PATH`: constant with the path of the page.
+/// - `open(&world)`: navigates to the page with `goto_path` and returns it.
+/// - `is_current(&world)`: checks if the current path relative to the host is
+///   the path of the page.
+/// - An accessor for each field, like `email(&world)`, that finds the element
+///   every time it's called, so the elements are never stale.
+///
+/// [worlder]: https://docs.rs/cucumber-thirtyfour-worlder/latest/cucumber_thirtyfour_worlder/attr.worlder.html
+#[proc_macro_derive(Page, attributes(page, locator))]
+pub fn page(stream: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(stream as syn::DeriveInput);
+    page::derive_page(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
 struct WorlderArgs {
     check_concurrency_cli_option_when_firefox: bool,
+    env_file: Option<syn::LitStr>,
+    hydration: Option<syn::LitStr>,
+    test_id_attribute: syn::LitStr,
+    tracing: Option<syn::Path>,
     cucumber: syn::Path,
     thirtyfour: syn::Path,
 }
@@ -509,6 +1382,10 @@ impl Default for WorlderArgs {
     fn default() -> Self {
         Self {
             check_concurrency_cli_option_when_firefox: true,
+            env_file: None,
+            hydration: None,
+            test_id_attribute: syn::LitStr::new("data-testid", proc_macro2::Span::call_site()),
+            tracing: None,
             cucumber: syn::parse_str::<syn::Path>("::cucumber").unwrap(),
             thirtyfour: syn::parse_str::<syn::Path>("::thirtyfour").unwrap(),
         }
@@ -524,6 +1401,45 @@ impl Parse for WorlderArgs {
                 input.parse::<syn::Token![=]>()?;
                 let value: syn::LitBool = input.parse()?;
                 args.check_concurrency_cli_option_when_firefox = value.value;
+            } else if ident == "env_file" {
+                input.parse::<syn::Token![=]>()?;
+                args.env_file = Some(input.parse()?);
+            } else if ident == "hydration" {
+                input.parse::<syn::Token![=]>()?;
+                let value: syn::LitStr = input.parse()?;
+                let hydration = value.value();
+                // A bare word would be evaluated as an undefined variable.
+                if hydration.chars().all(|c| c.is_ascii_alphabetic())
+                    && !wait::HYDRATION_PRESETS.contains(&hydration.as_str())
+                {
+                    return Err(syn::Error::new(
+                        value.span(),
+                        format!(
+                            "Unknown hydration preset: \"{hydration}\". Expected one of: \
+                            \"{}\", or a JavaScript expression",
+                            wait::HYDRATION_PRESETS.join("\", \""),
+                        ),
+                    ));
+                }
+                args.hydration = Some(value);
+            } else if ident == "test_id_attribute" {
+                input.parse::<syn::Token![=]>()?;
+                let value: syn::LitStr = input.parse()?;
+                let attribute = value.value();
+                if attribute.is_empty()
+                    || !attribute
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                {
+                    return Err(syn::Error::new(
+                        value.span(),
+                        format!("Invalid test id attribute name: \"{attribute}\""),
+                    ));
+                }
+                args.test_id_attribute = value;
+            } else if ident == "tracing" {
+                input.parse::<syn::Token![=]>()?;
+                args.tracing = Some(input.parse()?);
             } else if ident == "cucumber" {
                 input.parse::<syn::Token![=]>()?;
                 args.cucumber = input.parse()?;
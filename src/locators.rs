@@ -0,0 +1,500 @@
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use quote::quote;
+
+/// Find the visible elements of the page the way users perceive them, by their
+/// ARIA role and accessible name, label, placeholder or text, comparing the
+/// texts with normalized whitespaces. Computes a subset of the implicit roles
+/// and accessible names defined by the HTML-AAM and ACCNAME specifications.
+///
+/// Called with the kind of locator, the value, the accessible name (or `null`)
+/// and whether only the first element must be returned. Always returns an
+/// array, empty if no element is found.
+const LOCATOR_SCRIPT: &str = r"(function (kind, value, name, first) {
+    const normalize = (text) => (text || '').replace(/\s+/g, ' ').trim();
+    const labelsOf = (element) => {
+        const labels = [];
+        const labelledBy = normalize(element.getAttribute('aria-labelledby'));
+        if (labelledBy) {
+            labels.push(normalize(labelledBy.split(' ').map((id) => {
+                const reference = document.getElementById(id);
+                return reference ? reference.textContent : '';
+            }).join(' ')));
+        }
+        labels.push(normalize(element.getAttribute('aria-label')));
+        for (const label of element.labels || []) {
+            labels.push(normalize(label.textContent));
+        }
+        return labels.filter((label) => label);
+    };
+    const roleOf = (element) => {
+        const explicit = normalize(element.getAttribute('role')).split(' ')[0];
+        if (explicit) {
+            return explicit;
+        }
+        const type = (element.getAttribute('type') || '').toLowerCase();
+        const landmark = !element.closest('article, aside, main, nav, section');
+        switch (element.localName) {
+            case 'a':
+            case 'area':
+                return element.hasAttribute('href') ? 'link' : null;
+            case 'button':
+                return 'button';
+            case 'input':
+                if (['button', 'image', 'reset', 'submit'].includes(type)) return 'button';
+                if (type === 'checkbox') return 'checkbox';
+                if (type === 'radio') return 'radio';
+                if (type === 'range') return 'slider';
+                if (type === 'number') return 'spinbutton';
+                if (element.hasAttribute('list')) return 'combobox';
+                if (type === 'search') return 'searchbox';
+                return ['', 'email', 'tel', 'text', 'url'].includes(type) ? 'textbox' : null;
+            case 'textarea':
+                return 'textbox';
+            case 'select':
+                return element.multiple || element.size > 1 ? 'listbox' : 'combobox';
+            case 'option':
+                return 'option';
+            case 'h1': case 'h2': case 'h3': case 'h4': case 'h5': case 'h6':
+                return 'heading';
+            case 'img':
+                return element.getAttribute('alt') === '' ? 'presentation' : 'img';
+            case 'ul': case 'ol': case 'menu':
+                return 'list';
+            case 'li':
+                return 'listitem';
+            case 'nav':
+                return 'navigation';
+            case 'main':
+                return 'main';
+            case 'header':
+                return landmark ? 'banner' : null;
+            case 'footer':
+                return landmark ? 'contentinfo' : null;
+            case 'aside':
+                return 'complementary';
+            case 'article':
+                return 'article';
+            case 'section':
+                return labelsOf(element).length ? 'region' : null;
+            case 'form':
+                return 'form';
+            case 'dialog':
+                return 'dialog';
+            case 'fieldset':
+                return 'group';
+            case 'table':
+                return 'table';
+            case 'tr':
+                return 'row';
+            case 'td':
+                return 'cell';
+            case 'th':
+                return 'columnheader';
+            case 'progress':
+                return 'progressbar';
+            case 'hr':
+                return 'separator';
+            default:
+                return null;
+        }
+    };
+    const nameFromContent = [
+        'button', 'cell', 'checkbox', 'columnheader', 'heading', 'link', 'menuitem',
+        'option', 'radio', 'row', 'rowheader', 'switch', 'tab', 'tooltip', 'treeitem',
+    ];
+    const nameOf = (element, role) => {
+        const labels = labelsOf(element);
+        if (labels.length) {
+            return labels[0];
+        }
+        if (element.localName === 'img' || (element.localName === 'input' && element.type === 'image')) {
+            const alt = normalize(element.getAttribute('alt'));
+            if (alt) return alt;
+        }
+        if (element.localName === 'input' && ['button', 'reset', 'submit'].includes(element.type)) {
+            const text = normalize(element.value);
+            if (text) return text;
+        }
+        if (nameFromContent.includes(role)) {
+            const text = normalize(element.textContent);
+            if (text) return text;
+        }
+        return normalize(element.getAttribute('title') || element.getAttribute('placeholder'));
+    };
+    const hidden = (element) => element.closest('[hidden], [aria-hidden=true]') !== null
+        || (element.localName !== 'option' && element.getClientRects().length === 0)
+        || getComputedStyle(element).visibility === 'hidden';
+
+    const expected = normalize(value);
+    const elements = Array.from(document.body ? document.body.querySelectorAll('*') : []);
+    let matches;
+    switch (kind) {
+        case 'role':
+            matches = elements.filter((element) => roleOf(element) === expected
+                && (name === null || nameOf(element, expected) === normalize(name)));
+            break;
+        case 'label':
+            matches = elements.filter((element) => labelsOf(element).includes(expected));
+            break;
+        case 'placeholder':
+            matches = elements.filter((element) => element.hasAttribute('placeholder')
+                && normalize(element.getAttribute('placeholder')) === expected);
+            break;
+        case 'text': {
+            const text = (element) => !['script', 'style', 'template'].includes(element.localName)
+                && normalize(element.textContent) === expected;
+            matches = elements.filter((element) => text(element)
+                && !Array.from(element.children).some(text));
+            break;
+        }
+    }
+    matches = matches.filter((element) => !hidden(element));
+    return first ? matches.slice(0, 1) : matches;
+})";
+
+pub(crate) fn build_locator_enum(
+    thirtyfour: &syn::Path,
+    vis_ident: &TokenTree,
+    locator_ident: &Ident,
+) -> TokenStream {
+    quote! {
+        #[doc = "Locator of elements, either a `By` of thirtyfour or the way users perceive them."]
+        #[doc = ""]
+        #[doc = "Built with the `by_role`, `by_label`, `by_placeholder` and `by_text` functions of"]
+        #[doc = "the world or converted from a `By`, and accepted by the `locate`, `locate_all` and"]
+        #[doc = "`expect` methods of the world and by the fields of the pages."]
+        #[derive(Debug, Clone)]
+        #vis_ident enum #locator_ident {
+            #[doc = "Elements located by thirtyfour."]
+            By(#thirtyfour::By),
+            #[doc = "Visible elements with an ARIA role and, if defined, an accessible name."]
+            Role {
+                #[doc = "ARIA role, like `button`."]
+                role: String,
+                #[doc = "Accessible name, compared after normalizing whitespaces."]
+                name: Option<String>,
+            },
+            #[doc = "Visible elements labelled with a text."]
+            Label(String),
+            #[doc = "Visible elements with a placeholder."]
+            Placeholder(String),
+            #[doc = "Innermost visible elements with a text."]
+            Text(String),
+        }
+
+        impl From<#thirtyfour::By> for #locator_ident {
+            fn from(by: #thirtyfour::By) -> Self {
+                Self::By(by)
+            }
+        }
+    }
+}
+
+pub(crate) fn build_locators_fns(
+    thirtyfour: &syn::Path,
+    locator_ident: &Ident,
+    test_id_attribute: &str,
+) -> TokenStream {
+    quote! {
+        #[doc = concat!("Build a locator for the elements with the given test id in their `", #test_id_attribute, "` attribute.")]
+        #[doc = ""]
+        #[doc = "The attribute is defined by the `test_id_attribute` argument of the `#[worlder]` macro."]
+        #[must_use]
+        pub fn by_test_id(id: &str) -> #thirtyfour::By {
+            #thirtyfour::By::Css(format!(
+                "[{}={}]",
+                #test_id_attribute,
+                Self::__css_string(id),
+            ))
+        }
+
+        #[doc = "Find the first element with the given test id."]
+        #[doc = ""]
+        #[doc = "See [`by_test_id`](Self::by_test_id)."]
+        pub async fn find_by_test_id(
+            &self,
+            id: &str,
+        ) -> Result<#thirtyfour::WebElement, #thirtyfour::error::WebDriverError> {
+            self.driver().find(Self::by_test_id(id)).await
+        }
+
+        #[doc = "Find all the elements with the given test id."]
+        #[doc = ""]
+        #[doc = "See [`by_test_id`](Self::by_test_id)."]
+        pub async fn find_all_by_test_id(
+            &self,
+            id: &str,
+        ) -> Result<Vec<#thirtyfour::WebElement>, #thirtyfour::error::WebDriverError> {
+            self.driver().find_all(Self::by_test_id(id)).await
+        }
+
+        #[doc = "Build a locator for the visible elements with the given ARIA role and, if defined,"]
+        #[doc = "accessible name."]
+        #[doc = ""]
+        #[doc = "Both explicit roles and the implicit roles of the HTML elements are considered, like"]
+        #[doc = "`button` for `<button>` and `<input type=\"submit\">` or `heading` for `<h1>`. The"]
+        #[doc = "accessible name is computed from `aria-labelledby`, `aria-label`, the associated"]
+        #[doc = "labels, the `alt` attribute or the text content, and compared with the given one"]
+        #[doc = "after normalizing whitespaces."]
+        #[doc = ""]
+        #[doc = "```rust,ignore"]
+        #[doc = "world.expect(AppWorld::by_role(\"button\", Some(\"Save\"))).to_be_enabled().await;"]
+        #[doc = "```"]
+        #[must_use]
+        pub fn by_role(role: &str, name: Option<&str>) -> #locator_ident {
+            #locator_ident::Role {
+                role: role.to_string(),
+                name: name.map(ToString::to_string),
+            }
+        }
+
+        #[doc = "Build a locator for the visible elements labelled with the given text."]
+        #[doc = ""]
+        #[doc = "Matches the texts of the associated `<label>` elements, `aria-labelledby` and"]
+        #[doc = "`aria-label`, after normalizing whitespaces."]
+        #[must_use]
+        pub fn by_label(label: &str) -> #locator_ident {
+            #locator_ident::Label(label.to_string())
+        }
+
+        #[doc = "Build a locator for the visible elements with the given placeholder, after"]
+        #[doc = "normalizing whitespaces."]
+        #[must_use]
+        pub fn by_placeholder(placeholder: &str) -> #locator_ident {
+            #locator_ident::Placeholder(placeholder.to_string())
+        }
+
+        #[doc = "Build a locator for the visible elements with the given text, after normalizing"]
+        #[doc = "whitespaces."]
+        #[doc = ""]
+        #[doc = "Only the innermost elements with the text are matched, so `<p><b>Hello</b></p>`"]
+        #[doc = "matches the `<b>` element for `\"Hello\"`."]
+        #[must_use]
+        pub fn by_text(text: &str) -> #locator_ident {
+            #locator_ident::Text(text.to_string())
+        }
+
+        #[doc = "Find the first element matching the given locator."]
+        #[doc = ""]
+        #[doc = "Accepts a `By` or a locator built with [`by_role`](Self::by_role),"]
+        #[doc = "[`by_label`](Self::by_label), [`by_placeholder`](Self::by_placeholder) or"]
+        #[doc = "[`by_text`](Self::by_text)."]
+        #[doc = ""]
+        #[doc = "# Errors"]
+        #[doc = ""]
+        #[doc = "If no element matches the locator, returns a `NoSuchElement` error."]
+        pub async fn locate(
+            &self,
+            locator: impl Into<#locator_ident>,
+        ) -> Result<#thirtyfour::WebElement, #thirtyfour::error::WebDriverError> {
+            let locator = locator.into();
+            if let #locator_ident::By(by) = locator {
+                return self.driver().find(by).await;
+            }
+            self.__locate(&locator, true)
+                .await?
+                .pop()
+                .ok_or_else(|| #thirtyfour::error::no_such_element(format!("No element found by {locator:?}")))
+        }
+
+        #[doc = "Find all the elements matching the given locator."]
+        #[doc = ""]
+        #[doc = "See [`locate`](Self::locate)."]
+        pub async fn locate_all(
+            &self,
+            locator: impl Into<#locator_ident>,
+        ) -> Result<Vec<#thirtyfour::WebElement>, #thirtyfour::error::WebDriverError> {
+            let locator = locator.into();
+            if let #locator_ident::By(by) = locator {
+                return self.driver().find_all(by).await;
+            }
+            self.__locate(&locator, false).await
+        }
+
+        #[doc = "Find the first visible element with the given ARIA role and, if defined, accessible name."]
+        #[doc = ""]
+        #[doc = "See [`by_role`](Self::by_role)."]
+        #[doc = ""]
+        #[doc = "```rust,ignore"]
+        #[doc = "world.find_by_role(\"button\", Some(\"Save\")).await?.click().await?;"]
+        #[doc = "```"]
+        pub async fn find_by_role(
+            &self,
+            role: &str,
+            name: Option<&str>,
+        ) -> Result<#thirtyfour::WebElement, #thirtyfour::error::WebDriverError> {
+            self.locate(Self::by_role(role, name)).await
+        }
+
+        #[doc = "Find all the visible elements with the given ARIA role and, if defined, accessible name."]
+        #[doc = ""]
+        #[doc = "See [`by_role`](Self::by_role)."]
+        pub async fn find_all_by_role(
+            &self,
+            role: &str,
+            name: Option<&str>,
+        ) -> Result<Vec<#thirtyfour::WebElement>, #thirtyfour::error::WebDriverError> {
+            self.locate_all(Self::by_role(role, name)).await
+        }
+
+        #[doc = "Find the first visible element labelled with the given text."]
+        #[doc = ""]
+        #[doc = "See [`by_label`](Self::by_label)."]
+        pub async fn find_by_label(
+            &self,
+            label: &str,
+        ) -> Result<#thirtyfour::WebElement, #thirtyfour::error::WebDriverError> {
+            self.locate(Self::by_label(label)).await
+        }
+
+        #[doc = "Find all the visible elements labelled with the given text."]
+        #[doc = ""]
+        #[doc = "See [`by_label`](Self::by_label)."]
+        pub async fn find_all_by_label(
+            &self,
+            label: &str,
+        ) -> Result<Vec<#thirtyfour::WebElement>, #thirtyfour::error::WebDriverError> {
+            self.locate_all(Self::by_label(label)).await
+        }
+
+        #[doc = "Find the first visible element with the given placeholder, after normalizing whitespaces."]
+        pub async fn find_by_placeholder(
+            &self,
+            placeholder: &str,
+        ) -> Result<#thirtyfour::WebElement, #thirtyfour::error::WebDriverError> {
+            self.locate(Self::by_placeholder(placeholder)).await
+        }
+
+        #[doc = "Find all the visible elements with the given placeholder, after normalizing whitespaces."]
+        pub async fn find_all_by_placeholder(
+            &self,
+            placeholder: &str,
+        ) -> Result<Vec<#thirtyfour::WebElement>, #thirtyfour::error::WebDriverError> {
+            self.locate_all(Self::by_placeholder(placeholder)).await
+        }
+
+        #[doc = "Find the first visible element with the given text, after normalizing whitespaces."]
+        #[doc = ""]
+        #[doc = "See [`by_text`](Self::by_text)."]
+        pub async fn find_by_text(
+            &self,
+            text: &str,
+        ) -> Result<#thirtyfour::WebElement, #thirtyfour::error::WebDriverError> {
+            self.locate(Self::by_text(text)).await
+        }
+
+        #[doc = "Find all the visible elements with the given text, after normalizing whitespaces."]
+        #[doc = ""]
+        #[doc = "See [`by_text`](Self::by_text)."]
+        pub async fn find_all_by_text(
+            &self,
+            text: &str,
+        ) -> Result<Vec<#thirtyfour::WebElement>, #thirtyfour::error::WebDriverError> {
+            self.locate_all(Self::by_text(text)).await
+        }
+
+        #[doc = "Find the first element matching a path of CSS selectors separated by `>>`, piercing"]
+        #[doc = "the shadow roots of the elements matched by all but the last selector."]
+        #[doc = ""]
+        #[doc = "```rust,ignore"]
+        #[doc = "world.pierce(\"my-dialog >> button.ok\").await?.click().await?;"]
+        #[doc = "```"]
+        #[doc = ""]
+        #[doc = "# Panics"]
+        #[doc = ""]
+        #[doc = "If any of the selectors of the path is empty."]
+        pub async fn pierce(
+            &self,
+            path: &str,
+        ) -> Result<#thirtyfour::WebElement, #thirtyfour::error::WebDriverError> {
+            let (root, selector) = self.__pierce_root(path).await?;
+            match root {
+                Some(root) => root.find(#thirtyfour::By::Css(selector)).await,
+                None => self.driver().find(#thirtyfour::By::Css(selector)).await,
+            }
+        }
+
+        #[doc = "Find all the elements matching a path of CSS selectors separated by `>>`, piercing"]
+        #[doc = "the shadow roots of the elements matched by all but the last selector."]
+        #[doc = ""]
+        #[doc = "See [`pierce`](Self::pierce)."]
+        pub async fn pierce_all(
+            &self,
+            path: &str,
+        ) -> Result<Vec<#thirtyfour::WebElement>, #thirtyfour::error::WebDriverError> {
+            let (root, selector) = self.__pierce_root(path).await?;
+            match root {
+                Some(root) => root.find_all(#thirtyfour::By::Css(selector)).await,
+                None => self.driver().find_all(#thirtyfour::By::Css(selector)).await,
+            }
+        }
+
+        async fn __pierce_root(
+            &self,
+            path: &str,
+        ) -> Result<(Option<#thirtyfour::WebElement>, String), #thirtyfour::error::WebDriverError> {
+            let selectors = path.split(">>").map(str::trim).collect::<Vec<_>>();
+            assert!(
+                !selectors.contains(&""),
+                "Invalid shadow DOM path \"{path}\". Expected CSS selectors separated by \">>\", \
+                like \"my-dialog >> button.ok\"."
+            );
+            let (selector, hosts) = selectors.split_last().unwrap();
+            let mut root: Option<#thirtyfour::WebElement> = None;
+            for host in hosts {
+                let by = #thirtyfour::By::Css((*host).to_string());
+                let host = match &root {
+                    Some(root) => root.find(by).await?,
+                    None => self.driver().find(by).await?,
+                };
+                root = Some(host.get_shadow_root().await?);
+            }
+            Ok((root, (*selector).to_string()))
+        }
+
+        async fn __locate(
+            &self,
+            locator: &#locator_ident,
+            first: bool,
+        ) -> Result<Vec<#thirtyfour::WebElement>, #thirtyfour::error::WebDriverError> {
+            let (kind, value, name) = match locator {
+                #locator_ident::By(by) => {
+                    return self.driver().find_all(by.clone()).await;
+                }
+                #locator_ident::Role { role, name } => ("role", role, name.as_deref()),
+                #locator_ident::Label(label) => ("label", label, None),
+                #locator_ident::Placeholder(placeholder) => ("placeholder", placeholder, None),
+                #locator_ident::Text(text) => ("text", text, None),
+            };
+            let script = format!(
+                "return {}({}, {}, {}, {first});",
+                #LOCATOR_SCRIPT,
+                Self::__js_string(kind),
+                Self::__js_string(value),
+                name.map_or_else(|| "null".to_string(), Self::__js_string),
+            );
+            self.driver().execute(script, vec![]).await?.elements()
+        }
+
+        fn __css_string(value: &str) -> String {
+            use std::fmt::Write as _;
+
+            let mut escaped = String::with_capacity(value.len() + 2);
+            escaped.push('"');
+            for c in value.chars() {
+                match c {
+                    '"' | '\\' => {
+                        escaped.push('\\');
+                        escaped.push(c);
+                    }
+                    c if c.is_control() => {
+                        _ = write!(escaped, "\\{:x} ", u32::from(c));
+                    }
+                    c => escaped.push(c),
+                }
+            }
+            escaped.push('"');
+            escaped
+        }
+    }
+}
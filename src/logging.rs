@@ -0,0 +1,50 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Messages of the world about its features, like the artifacts it saves.
+///
+/// They're emitted as events of the `tracing` crate passed to the macro, so
+/// the subscriber of the user filters them, or written to stderr according to
+/// the `WORLDER_LOG` environment variable otherwise.
+pub(crate) fn build_logging_fns(tracing: Option<&syn::Path>) -> TokenStream {
+    let (warn, info) = match tracing {
+        Some(tracing) => (
+            quote!(#tracing::warn!("{message}")),
+            quote!(#tracing::info!("{message}")),
+        ),
+        None => (
+            quote! {
+                if Self::__discover_log_level().is_ok_and(|level| level != "off") {
+                    eprintln!("{message}");
+                }
+            },
+            quote! {
+                if Self::__discover_log_level().is_ok_and(|level| level == "info") {
+                    eprintln!("{message}");
+                }
+            },
+        ),
+    };
+
+    quote! {
+        fn __warn(message: &str) {
+            #warn
+        }
+
+        fn __info(message: &str) {
+            #info
+        }
+
+        fn __discover_log_level() -> Result<String, String> {
+            let level = Self::__config_value("WORLDER_LOG")
+                .map_or("warn".to_string(), |value| value.trim().to_ascii_lowercase());
+            if !matches!(level.as_str(), "off" | "warn" | "info") {
+                return Err(format!(
+                    "Invalid WORLDER_LOG environment variable value \"{level}\". \
+                    Expected one of: \"off\", \"warn\" or \"info\"."
+                ));
+            }
+            Ok(level)
+        }
+    }
+}
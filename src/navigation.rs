@@ -0,0 +1,403 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+pub(crate) fn build_navigation_fns(thirtyfour: &syn::Path) -> TokenStream {
+    quote! {
+        #[doc = "Navigate to the given path inside the host."]
+        #[doc = ""]
+        #[doc = "The URL is built with [`url_for`](Self::url_for). If the `hydration` argument"]
+        #[doc = "of the `#[worlder]` macro is defined, waits for the page to be hydrated."]
+        pub async fn goto_path(&self, path: &str) -> Result<&Self, #thirtyfour::error::WebDriverError> {
+            self.__before_navigation().await;
+            self.driver().goto(self.url_for(path)?).await?;
+            self.__after_navigation().await?;
+            Ok(self)
+        }
+
+        #[doc = "Navigate to the given path inside the host, appending the given query parameters."]
+        #[doc = ""]
+        #[doc = "The URL is built with [`url_with_query`](Self::url_with_query)."]
+        pub async fn goto_with_query(
+            &self,
+            path: &str,
+            query: &[(&str, &str)],
+        ) -> Result<&Self, #thirtyfour::error::WebDriverError> {
+            self.__before_navigation().await;
+            self.driver().goto(self.url_with_query(path, query)?).await?;
+            self.__after_navigation().await?;
+            Ok(self)
+        }
+
+        #[doc = "Navigate to the given path inside a named host."]
+        #[doc = ""]
+        #[doc = "See [`host_url_for`](Self::host_url_for) for how named hosts are defined."]
+        pub async fn goto(&self, host: &str, path: &str) -> Result<&Self, #thirtyfour::error::WebDriverError> {
+            self.__before_navigation().await;
+            self.driver().goto(self.url_for_host(host, path)?).await?;
+            self.__after_navigation().await?;
+            Ok(self)
+        }
+
+        #[doc = "Build the URL of the given path inside the host."]
+        #[doc = ""]
+        #[doc = "The path is joined to the host URL preserving its base path, so with a host URL"]
+        #[doc = "`http://localhost:8080/app/` the path `/login` resolves to"]
+        #[doc = "`http://localhost:8080/app/login`. Repeated slashes are collapsed and characters"]
+        #[doc = "not allowed in paths, queries and fragments are percent-encoded."]
+        #[doc = ""]
+        #[doc = "Absolute URLs are returned as is if they point to the host, to a named host or to"]
+        #[doc = "a host allowed with [`allow_foreign_host`](Self::allow_foreign_host), and"]
+        #[doc = "rejected with an invalid argument error otherwise."]
+        pub fn url_for(&self, path: &str) -> Result<String, #thirtyfour::error::WebDriverError> {
+            self.__join_url(&self.host_url, path)
+        }
+
+        #[doc = "Build the URL of the given path inside a named host."]
+        #[doc = ""]
+        #[doc = "See [`url_for`](Self::url_for) for how the URL is built. Hosts not defined are"]
+        #[doc = "rejected with an invalid argument error."]
+        pub fn url_for_host(&self, host: &str, path: &str) -> Result<String, #thirtyfour::error::WebDriverError> {
+            let base = self.hosts.get(&host.to_ascii_lowercase()).ok_or_else(|| {
+                Self::__invalid_argument(format!(
+                    "Host \"{host}\" is not defined. Define it with the \
+                    HOST_URL_{} environment variable.",
+                    host.to_ascii_uppercase(),
+                ))
+            })?;
+            self.__join_url(base, path)
+        }
+
+        #[doc = "Build the URL of the given path inside the host, appending the given query parameters."]
+        #[doc = ""]
+        #[doc = "Names and values of the parameters are percent-encoded and the parameters are"]
+        #[doc = "appended to the query of the path, if any, before its fragment."]
+        pub fn url_with_query(
+            &self,
+            path: &str,
+            query: &[(&str, &str)],
+        ) -> Result<String, #thirtyfour::error::WebDriverError> {
+            let url = self.url_for(path)?;
+            if query.is_empty() {
+                return Ok(url);
+            }
+            let (url, fragment) = match url.split_once('#') {
+                Some((url, fragment)) => (url, Some(fragment)),
+                None => (url.as_str(), None),
+            };
+            let query = query
+                .iter()
+                .map(|(name, value)| {
+                    format!(
+                        "{}={}",
+                        Self::__percent_encode(name, ""),
+                        Self::__percent_encode(value, ""),
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("&");
+            let separator = if url.contains('?') { '&' } else { '?' };
+            Ok(match fragment {
+                Some(fragment) => format!("{url}{separator}{query}#{fragment}"),
+                None => format!("{url}{separator}{query}"),
+            })
+        }
+
+        #[doc = "Fill a path template replacing `{name}` placeholders with the given parameters."]
+        #[doc = ""]
+        #[doc = "Values are percent-encoded, so `path_for(\"/users/{id}\", &[(\"id\", \"a/b\")])`"]
+        #[doc = "returns `/users/a%2Fb`."]
+        #[doc = ""]
+        #[doc = "# Panics"]
+        #[doc = ""]
+        #[doc = "If a placeholder of the template is not closed or has no parameter."]
+        #[must_use]
+        pub fn path_for(template: &str, params: &[(&str, &str)]) -> String {
+            let mut path = String::with_capacity(template.len());
+            let mut rest = template;
+            while let Some(start) = rest.find('{') {
+                path.push_str(&rest[..start]);
+                let Some(end) = rest[start..].find('}') else {
+                    panic!("Unclosed placeholder in path template \"{template}\"");
+                };
+                let name = &rest[start + 1..start + end];
+                let (_, value) = params
+                    .iter()
+                    .find(|(param, _)| *param == name)
+                    .unwrap_or_else(|| {
+                        panic!("Missing parameter \"{name}\" for path template \"{template}\"")
+                    });
+                path.push_str(&Self::__percent_encode(value, ""));
+                rest = &rest[start + end + 1..];
+            }
+            path.push_str(rest);
+            path
+        }
+
+        #[doc = "Allow [`url_for`](Self::url_for) and the navigation methods to use absolute URLs"]
+        #[doc = "pointing to the given host, like `https://accounts.example.com`."]
+        pub fn allow_foreign_host(&mut self, url: &str) -> &mut Self {
+            self.allowed_hosts.push(Self::__url_origin(url));
+            self
+        }
+
+        #[doc = "Get the path of the current URL relative to the host, percent-decoded."]
+        #[doc = ""]
+        #[doc = "With a host URL `http://localhost:8080/app/` and the current URL"]
+        #[doc = "`http://localhost:8080/app/users?page=2`, returns `/users`. If the current URL is"]
+        #[doc = "not inside the host, returns it without query nor fragment."]
+        pub async fn current_path(&self) -> Result<String, #thirtyfour::error::WebDriverError> {
+            let url = self.driver().current_url().await?.to_string();
+            Ok(self.__relative_path(&url))
+        }
+
+        #[doc = "Get the query parameters of the current URL, percent-decoded."]
+        pub async fn current_query(&self) -> Result<Vec<(String, String)>, #thirtyfour::error::WebDriverError> {
+            let url = self.driver().current_url().await?.to_string();
+            let query = url
+                .split('#')
+                .next()
+                .and_then(|url| url.split_once('?'))
+                .map_or("", |(_, query)| query);
+            Ok(query
+                .split('&')
+                .filter(|param| !param.is_empty())
+                .map(|param| {
+                    let (name, value) = param.split_once('=').unwrap_or((param, ""));
+                    (
+                        Self::__percent_decode(name, true),
+                        Self::__percent_decode(value, true),
+                    )
+                })
+                .collect())
+        }
+
+        #[doc = "Wait until the path of the current URL relative to the host matches the given"]
+        #[doc = "glob pattern, up to [`ready_timeout`](Self::ready_timeout)."]
+        #[doc = ""]
+        #[doc = "In the pattern, `*` matches any characters except `/`, `**` matches any characters"]
+        #[doc = "and `?` matches a single character, so `/users/*/edit` matches `/users/42/edit`."]
+        #[doc = ""]
+        #[doc = "# Errors"]
+        #[doc = ""]
+        #[doc = "If the path doesn't match before the timeout, returns a `Timeout` error."]
+        pub async fn wait_for_path(&self, pattern: &str) -> Result<&Self, #thirtyfour::error::WebDriverError> {
+            let deadline = std::time::Instant::now() + self.ready_timeout;
+            loop {
+                let path = self.current_path().await?;
+                if Self::__glob_match(pattern.as_bytes(), path.as_bytes()) {
+                    return Ok(self);
+                }
+                if std::time::Instant::now() >= deadline {
+                    return Err(#thirtyfour::error::WebDriverError::Timeout(format!(
+                        "Timed out after {:?} waiting for the path to match \"{pattern}\". \
+                        Current path is \"{path}\".",
+                        self.ready_timeout,
+                    )));
+                }
+                #thirtyfour::support::sleep(std::time::Duration::from_millis(100)).await;
+            }
+        }
+
+        #[doc = "Wait until the path of the current URL relative to the host matches the given"]
+        #[doc = "JavaScript regular expression, up to [`ready_timeout`](Self::ready_timeout)."]
+        #[doc = ""]
+        #[doc = "# Errors"]
+        #[doc = ""]
+        #[doc = "If the path doesn't match before the timeout, returns a `Timeout` error."]
+        pub async fn wait_for_path_regex(&self, regex: &str) -> Result<&Self, #thirtyfour::error::WebDriverError> {
+            let deadline = std::time::Instant::now() + self.ready_timeout;
+            loop {
+                let path = self.current_path().await?;
+                let script = format!(
+                    "return new RegExp({}).test({});",
+                    Self::__js_string(regex),
+                    Self::__js_string(&path),
+                );
+                if self.driver().execute(script, vec![]).await?.convert::<bool>()? {
+                    return Ok(self);
+                }
+                if std::time::Instant::now() >= deadline {
+                    return Err(#thirtyfour::error::WebDriverError::Timeout(format!(
+                        "Timed out after {:?} waiting for the path to match /{regex}/. \
+                        Current path is \"{path}\".",
+                        self.ready_timeout,
+                    )));
+                }
+                #thirtyfour::support::sleep(std::time::Duration::from_millis(100)).await;
+            }
+        }
+
+        #[doc = "Assert that the path of the current URL relative to the host is the given one."]
+        #[doc = ""]
+        #[doc = "# Panics"]
+        #[doc = ""]
+        #[doc = "If the path is different, showing where the expected and the current path differ."]
+        pub async fn assert_path(&self, expected: &str) -> Result<&Self, #thirtyfour::error::WebDriverError> {
+            let actual = self.current_path().await?;
+            if actual != expected {
+                let column = expected
+                    .chars()
+                    .zip(actual.chars())
+                    .take_while(|(expected, actual)| expected == actual)
+                    .count();
+                panic!(
+                    "The current path is not the expected one.\n\
+                    expected: {expected}\n  \
+                    actual: {actual}\n          \
+                    {}^",
+                    " ".repeat(column),
+                );
+            }
+            Ok(self)
+        }
+
+        fn __relative_path(&self, url: &str) -> String {
+            let url = url.split(['?', '#']).next().unwrap_or_default();
+            let base = self
+                .host_url
+                .split(['?', '#'])
+                .next()
+                .unwrap_or_default()
+                .trim_end_matches('/');
+            match url.strip_prefix(base) {
+                Some("") => "/".to_string(),
+                Some(path) if path.starts_with('/') => Self::__percent_decode(path, false),
+                _ => url.to_string(),
+            }
+        }
+
+        fn __glob_match(pattern: &[u8], path: &[u8]) -> bool {
+            match pattern {
+                [] => path.is_empty(),
+                [b'*', b'*', rest @ ..] => {
+                    (0..=path.len()).any(|index| Self::__glob_match(rest, &path[index..]))
+                }
+                [b'*', rest @ ..] => (0..=path.len())
+                    .take_while(|index| *index == 0 || path[index - 1] != b'/')
+                    .any(|index| Self::__glob_match(rest, &path[index..])),
+                [b'?', rest @ ..] => !path.is_empty() && Self::__glob_match(rest, &path[1..]),
+                [c, rest @ ..] => path.first() == Some(c) && Self::__glob_match(rest, &path[1..]),
+            }
+        }
+
+        fn __percent_decode(value: &str, plus_as_space: bool) -> String {
+            let bytes = value.as_bytes();
+            let mut decoded = Vec::with_capacity(bytes.len());
+            let mut index = 0;
+            while index < bytes.len() {
+                let escaped = bytes
+                    .get(index + 1..index + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match (bytes[index], escaped) {
+                    (b'%', Some(byte)) => {
+                        decoded.push(byte);
+                        index += 2;
+                    }
+                    (b'+', _) if plus_as_space => decoded.push(b' '),
+                    (byte, _) => decoded.push(byte),
+                }
+                index += 1;
+            }
+            String::from_utf8_lossy(&decoded).into_owned()
+        }
+
+        fn __join_url(&self, base: &str, path: &str) -> Result<String, #thirtyfour::error::WebDriverError> {
+            if Self::__is_absolute_url(path) {
+                let origin = Self::__url_origin(path);
+                let allowed = std::iter::once(self.host_url.as_str())
+                    .chain(self.hosts.values().map(String::as_str))
+                    .map(Self::__url_origin)
+                    .chain(self.allowed_hosts.iter().cloned())
+                    .any(|allowed| allowed == origin);
+                if !allowed {
+                    return Err(Self::__invalid_argument(format!(
+                        "Refusing to navigate to {path} because {origin} is not the host \
+                        of the application under test. Allow it with `allow_foreign_host`."
+                    )));
+                }
+                return Ok(path.to_string());
+            }
+            Ok(Self::__join_path(base, path))
+        }
+
+        fn __invalid_argument(message: String) -> #thirtyfour::error::WebDriverError {
+            #thirtyfour::error::WebDriverError::InvalidArgument(
+                #thirtyfour::error::WebDriverErrorInfo::new(message),
+            )
+        }
+
+        // Whether the path starts with a scheme, so URLs in queries or fragments, like
+        // `/login?next=https://app/home`, don't make it absolute.
+        fn __is_absolute_url(path: &str) -> bool {
+            let Some(end) = path.find([':', '/', '?', '#']) else {
+                return false;
+            };
+            let scheme = &path[..end];
+            path[end..].starts_with(':')
+                && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        }
+
+        fn __join_path(base: &str, path: &str) -> String {
+            let (path, fragment) = match path.split_once('#') {
+                Some((path, fragment)) => (path, Some(fragment)),
+                None => (path, None),
+            };
+            let (path, query) = match path.split_once('?') {
+                Some((path, query)) => (path, Some(query)),
+                None => (path, None),
+            };
+            let base = base.split(['?', '#']).next().unwrap_or_default();
+            let mut url = base.trim_end_matches('/').to_string();
+            for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+                url.push('/');
+                url.push_str(&Self::__percent_encode(segment, "!$&'()*+,;=:@%"));
+            }
+            if path.ends_with('/') || !path.contains(|c| c != '/') {
+                url.push('/');
+            }
+            if let Some(query) = query {
+                url.push('?');
+                url.push_str(&Self::__percent_encode(query, "!$&'()*+,;=:@/?%"));
+            }
+            if let Some(fragment) = fragment {
+                url.push('#');
+                url.push_str(&Self::__percent_encode(fragment, "!$&'()*+,;=:@/?%"));
+            }
+            url
+        }
+
+        fn __url_origin(url: &str) -> String {
+            let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+            let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+            format!("{scheme}://{authority}").to_ascii_lowercase()
+        }
+
+        // Encode the characters not allowed. When `%` is allowed, only the valid escapes
+        // are kept, so values already encoded are not encoded twice.
+        fn __percent_encode(value: &str, allowed: &str) -> String {
+            use std::fmt::Write as _;
+
+            let mut encoded = String::with_capacity(value.len());
+            for (index, c) in value.char_indices() {
+                let escape = c == '%'
+                    && value
+                        .as_bytes()
+                        .get(index + 1..index + 3)
+                        .is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit));
+                if c.is_ascii_alphanumeric()
+                    || "-._~".contains(c)
+                    || (allowed.contains(c) && (c != '%' || escape))
+                {
+                    encoded.push(c);
+                } else {
+                    for byte in c.to_string().bytes() {
+                        _ = write!(encoded, "%{byte:02X}");
+                    }
+                }
+            }
+            encoded
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+
+pub(crate) fn derive_page(input: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let Some(attr) = input.attrs.iter().find(|attr| attr.path().is_ident("page")) else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "#[derive(Page)] requires a #[page(path = \"...\", world = ...)] attribute",
+        ));
+    };
+    let args = attr.parse_args::<PageArgs>()?;
+    let (Some(path), Some(world)) = (args.path, args.world) else {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "#[page] requires the `path` and `world` arguments",
+        ));
+    };
+    let thirtyfour = args.thirtyfour;
+
+    let named = match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => fields.named.iter().collect(),
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Unit,
+            ..
+        }) => vec![],
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "#[derive(Page)] requires a struct with named fields or a unit struct",
+            ));
+        }
+    };
+
+    let mut locators = vec![];
+    for field in named {
+        let Some(attr) = field
+            .attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("locator"))
+        else {
+            return Err(syn::Error::new_spanned(
+                field,
+                "Fields of pages require a #[locator(...)] attribute, like #[locator(css = \"#email\")]",
+            ));
+        };
+        let locator = attr.parse_args::<Locator>()?;
+        locators.push((field.ident.clone().unwrap(), locator));
+    }
+    Ok(build_page(input, &path, &world, &thirtyfour, &locators))
+}
+
+fn build_page(
+    input: &syn::DeriveInput,
+    path: &syn::LitStr,
+    world: &syn::Path,
+    thirtyfour: &syn::Path,
+    locators: &[(syn::Ident, Locator)],
+) -> TokenStream {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let initializers = locators.iter().map(|(field, locator)| {
+        let value = &locator.value;
+        let by = match locator.kind.to_string().as_str() {
+            "test_id" => quote!(#world::by_test_id(#value)),
+            "css" => quote!(#thirtyfour::By::Css(#value)),
+            "xpath" => quote!(#thirtyfour::By::XPath(#value)),
+            "id" => quote!(#thirtyfour::By::Id(#value)),
+            "name" => quote!(#thirtyfour::By::Name(#value)),
+            "tag" => quote!(#thirtyfour::By::Tag(#value)),
+            "class_name" => quote!(#thirtyfour::By::ClassName(#value)),
+            "link_text" => quote!(#thirtyfour::By::LinkText(#value)),
+            "role" => {
+                let name = locator
+                    .name
+                    .as_ref()
+                    .map_or_else(|| quote!(None), |name| quote!(Some(#name)));
+                quote!(#world::by_role(#value, #name))
+            }
+            "label" => quote!(#world::by_label(#value)),
+            "placeholder" => quote!(#world::by_placeholder(#value)),
+            _ => quote!(#world::by_text(#value)),
+        };
+        quote!(#field: #by.into())
+    });
+    let accessors = locators.iter().map(|(field, locator)| {
+        let doc = match &locator.name {
+            Some(name) => format!(
+                "Find the `{field}` element of the page, located by {} `\"{}\"` with name `\"{}\"`.",
+                locator.kind,
+                locator.value.value(),
+                name.value(),
+            ),
+            None => format!(
+                "Find the `{field}` element of the page, located by {} `\"{}\"`.",
+                locator.kind,
+                locator.value.value(),
+            ),
+        };
+        quote! {
+            #[doc = #doc]
+            #[doc = ""]
+            #[doc = "The element is found every time this method is called, so it's never stale."]
+            pub async fn #field(
+                &self,
+                world: &#world,
+            ) -> Result<#thirtyfour::WebElement, #thirtyfour::error::WebDriverError> {
+                world.locate(self.#field.clone()).await
+            }
+        }
+    });
+    let fields = if matches!(
+        input.data,
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Unit,
+            ..
+        })
+    ) {
+        quote!()
+    } else {
+        quote!({ #(#initializers,)* })
+    };
+
+    quote! {
+        impl #impl_generics Default for #ident #ty_generics #where_clause {
+            fn default() -> Self {
+                Self #fields
+            }
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #[doc = "Path of the page inside the host."]
+            pub const PATH: &'static str = #path;
+
+            #[doc = "Navigate to the page with the `goto_path` method of the world."]
+            pub async fn open(world: &#world) -> Result<Self, #thirtyfour::error::WebDriverError> {
+                world.goto_path(Self::PATH).await?;
+                Ok(Self::default())
+            }
+
+            #[doc = "Check if the path of the current URL relative to the host is the path of the page."]
+            pub async fn is_current(world: &#world) -> Result<bool, #thirtyfour::error::WebDriverError> {
+                Ok(world.current_path().await? == Self::PATH)
+            }
+
+            #(#accessors)*
+        }
+    }
+}
+
+struct PageArgs {
+    path: Option<syn::LitStr>,
+    world: Option<syn::Path>,
+    thirtyfour: syn::Path,
+}
+
+impl Parse for PageArgs {
+    fn parse(input: ParseStream) -> syn::parse::Result<Self> {
+        let mut args = PageArgs {
+            path: None,
+            world: None,
+            thirtyfour: syn::parse_str::<syn::Path>("::thirtyfour").unwrap(),
+        };
+        while !input.is_empty() {
+            let ident: syn::Ident = input.parse()?;
+            input.parse::<syn::Token![=]>()?;
+            if ident == "path" {
+                let path: syn::LitStr = input.parse()?;
+                if !path.value().starts_with('/') {
+                    return Err(syn::Error::new(
+                        path.span(),
+                        format!(
+                            "The path of the page must start with '/': \"{}\"",
+                            path.value()
+                        ),
+                    ));
+                }
+                args.path = Some(path);
+            } else if ident == "world" {
+                args.world = Some(input.parse()?);
+            } else if ident == "thirtyfour" {
+                args.thirtyfour = input.parse()?;
+            } else {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    format!("Unknown argument: {ident}"),
+                ));
+            }
+            if !input.is_empty() {
+                input.parse::<syn::Token![,]>()?;
+            }
+        }
+        Ok(args)
+    }
+}
+
+struct Locator {
+    kind: syn::Ident,
+    value: syn::LitStr,
+    name: Option<syn::LitStr>,
+}
+
+impl Parse for Locator {
+    fn parse(input: ParseStream) -> syn::parse::Result<Self> {
+        let kind: syn::Ident = input.parse()?;
+        if !matches!(
+            kind.to_string().as_str(),
+            "css"
+                | "xpath"
+                | "id"
+                | "name"
+                | "tag"
+                | "class_name"
+                | "link_text"
+                | "test_id"
+                | "role"
+                | "label"
+                | "placeholder"
+                | "text"
+        ) {
+            return Err(syn::Error::new(
+                kind.span(),
+                format!(
+                    "Unknown locator: {kind}. Expected one of: css, xpath, id, name, tag, \
+                    class_name, link_text, test_id, role, label, placeholder or text"
+                ),
+            ));
+        }
+        input.parse::<syn::Token![=]>()?;
+        let value = input.parse()?;
+        let mut name = None;
+        if kind == "role" && input.peek(syn::Token![,]) && input.peek2(syn::Ident) {
+            input.parse::<syn::Token![,]>()?;
+            let ident: syn::Ident = input.parse()?;
+            if ident != "name" {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    format!("Unknown argument of role locator: {ident}. Expected name"),
+                ));
+            }
+            input.parse::<syn::Token![=]>()?;
+            name = Some(input.parse()?);
+        }
+        input.parse::<Option<syn::Token![,]>>()?;
+        Ok(Self { kind, value, name })
+    }
+}
@@ -0,0 +1,493 @@
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use quote::{format_ident, quote};
+
+pub(crate) fn build_report_structs(
+    cucumber: &syn::Path,
+    vis_ident: &TokenTree,
+    struct_name_ident: &TokenTree,
+    report_ident: &Ident,
+) -> TokenStream {
+    let feature_ident = format_ident!("{}Feature", report_ident);
+    let scenario_ident = format_ident!("{}Scenario", report_ident);
+    let step_ident = format_ident!("{}Step", report_ident);
+    let feature_doc = format!("Feature of a [`{report_ident}`].");
+    let scenario_doc = format!("Scenario of a [`{feature_ident}`].");
+    let step_doc = format!("Step or hook of a [`{scenario_ident}`].");
+
+    quote! {
+        #[doc = "Report of a run of the features of the world."]
+        #[doc = ""]
+        #[doc = "It's collected by the writer of the world from the events of the run, and rendered"]
+        #[doc = "at the end of the run to the reports enabled by the environment variables."]
+        #[derive(Debug, Default)]
+        #vis_ident struct #report_ident {
+            #[doc = "Features in the order they were started."]
+            pub features: Vec<#feature_ident>,
+            artifacts_dir: Option<std::path::PathBuf>,
+        }
+
+        #[doc = #feature_doc]
+        #[derive(Debug)]
+        #vis_ident struct #feature_ident {
+            #[doc = "Keyword of the feature, like `Feature`."]
+            pub keyword: String,
+            #[doc = "Name of the feature."]
+            pub name: String,
+            #[doc = "Description of the feature."]
+            pub description: String,
+            #[doc = "Path of the file of the feature."]
+            pub path: Option<std::path::PathBuf>,
+            #[doc = "Line of the feature in its file."]
+            pub line: usize,
+            #[doc = "Tags of the feature, without the leading `@`."]
+            pub tags: Vec<String>,
+            #[doc = "Scenarios of the feature in the order they were started."]
+            pub scenarios: Vec<#scenario_ident>,
+        }
+
+        #[doc = #scenario_doc]
+        #[derive(Debug)]
+        #vis_ident struct #scenario_ident {
+            #[doc = "Keyword of the scenario, like `Scenario` or `Scenario Outline`."]
+            pub keyword: String,
+            #[doc = "Name of the scenario."]
+            pub name: String,
+            #[doc = "Description of the scenario."]
+            pub description: String,
+            #[doc = "Name of the rule of the scenario, if any."]
+            pub rule: Option<String>,
+            #[doc = "Line of the scenario in the file of the feature."]
+            pub line: usize,
+            #[doc = "Tags of the scenario, without the leading `@`."]
+            pub tags: Vec<String>,
+            #[doc = "Names and values of the row of the examples of a scenario outline."]
+            pub parameters: Vec<(String, String)>,
+            #[doc = "Time when the scenario started."]
+            pub started: std::time::SystemTime,
+            #[doc = "Duration of the scenario."]
+            pub duration: std::time::Duration,
+            #[doc = "Hooks, background steps and steps of the scenario in the order they ran."]
+            pub steps: Vec<#step_ident>,
+        }
+
+        #[doc = #step_doc]
+        #[derive(Debug)]
+        #vis_ident struct #step_ident {
+            #[doc = "Part of the scenario: `\"before\"` or `\"after\"` for hooks, `\"background\"` for"]
+            #[doc = "the steps of the background and `\"scenario\"` for the rest."]
+            pub section: &'static str,
+            #[doc = "Keyword of the step, like `Given `, or `Before` and `After` for hooks."]
+            pub keyword: String,
+            #[doc = "Text of the step, empty for hooks."]
+            pub name: String,
+            #[doc = "Line of the step in the file of the feature, `0` for hooks."]
+            pub line: usize,
+            #[doc = "Status of the step: `\"passed\"`, `\"failed\"` or `\"skipped\"`."]
+            pub status: &'static str,
+            #[doc = "Time when the step started."]
+            pub started: std::time::SystemTime,
+            #[doc = "Duration of the step."]
+            pub duration: std::time::Duration,
+            #[doc = "Error of the failed step."]
+            pub error: Option<String>,
+            #[doc = "Paths of the artifacts saved for the step, like screenshots and page sources."]
+            pub attachments: Vec<std::path::PathBuf>,
+        }
+
+        impl #scenario_ident {
+            #[doc = "Get the status of the scenario: `\"failed\"` when a step or a hook failed,"]
+            #[doc = "`\"skipped\"` when a step was skipped and `\"passed\"` otherwise."]
+            #[must_use]
+            pub fn status(&self) -> &'static str {
+                if self.steps.iter().any(|step| step.status == "failed") {
+                    "failed"
+                } else if self.steps.iter().any(|step| step.status == "skipped") {
+                    "skipped"
+                } else {
+                    "passed"
+                }
+            }
+        }
+
+        impl #step_ident {
+            fn __start(section: &'static str, keyword: String, name: String, line: usize) -> Self {
+                Self {
+                    section,
+                    keyword,
+                    name,
+                    line,
+                    status: "skipped",
+                    started: std::time::SystemTime::now(),
+                    duration: std::time::Duration::ZERO,
+                    error: None,
+                    attachments: vec![],
+                }
+            }
+
+            fn __finish(&mut self, status: &'static str, error: Option<String>) {
+                self.status = status;
+                self.duration = self.started.elapsed().unwrap_or_default();
+                self.error = error;
+            }
+        }
+
+        impl #report_ident {
+            #[doc = "Render the report in the Cucumber JSON format, embedding the artifacts saved for"]
+            #[doc = "the steps, like screenshots, console logs and page sources."]
+            #[must_use]
+            pub fn to_cucumber_json(&self) -> String {
+                let json = #struct_name_ident::__json_string;
+                let id = |name: &str| name.to_lowercase().split_whitespace().collect::<Vec<_>>().join("-");
+                let tags = |tags: &[String], line: usize| {
+                    tags.iter()
+                        .map(|tag| format!("{{\"name\": {}, \"line\": {line}}}", json(&format!("@{tag}"))))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+
+                let mut features = vec![];
+                for feature in &self.features {
+                    let mut elements = vec![];
+                    for scenario in &feature.scenarios {
+                        let section = |section: &str| {
+                            scenario
+                                .steps
+                                .iter()
+                                .filter(|step| step.section == section)
+                                .map(Self::__cucumber_json_step)
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        };
+                        let background = section("background");
+                        if !background.is_empty() {
+                            elements.push(format!(
+                                "{{\"keyword\": \"Background\", \"type\": \"background\", \"name\": \"\", \
+                                \"description\": \"\", \"line\": {}, \"steps\": [{background}]}}",
+                                scenario.steps.iter().find(|step| step.section == "background").map_or(0, |step| step.line),
+                            ));
+                        }
+                        elements.push(format!(
+                            "{{\"id\": {}, \"keyword\": {}, \"type\": \"scenario\", \"name\": {}, \
+                            \"description\": {}, \"line\": {}, \"tags\": [{}], \"before\": [{}], \
+                            \"steps\": [{}], \"after\": [{}]}}",
+                            json(&format!("{};{}", id(&feature.name), id(&scenario.name))),
+                            json(&scenario.keyword),
+                            json(&scenario.name),
+                            json(&scenario.description),
+                            scenario.line,
+                            tags(&scenario.tags, scenario.line),
+                            section("before"),
+                            section("scenario"),
+                            section("after"),
+                        ));
+                    }
+                    features.push(format!(
+                        "{{\"uri\": {}, \"id\": {}, \"keyword\": {}, \"name\": {}, \"description\": {}, \
+                        \"line\": {}, \"tags\": [{}], \"elements\": [\n    {}\n  ]}}",
+                        json(&feature.path.as_ref().map(|path| path.display().to_string()).unwrap_or_default()),
+                        json(&id(&feature.name)),
+                        json(&feature.keyword),
+                        json(&feature.name),
+                        json(&feature.description),
+                        feature.line,
+                        tags(&feature.tags, feature.line),
+                        elements.join(",\n    "),
+                    ));
+                }
+                format!("[\n  {}\n]\n", features.join(",\n  "))
+            }
+
+            fn __cucumber_json_step(step: &#step_ident) -> String {
+                let json = #struct_name_ident::__json_string;
+                let error = step
+                    .error
+                    .as_ref()
+                    .map(|error| format!(", \"error_message\": {}", json(error)))
+                    .unwrap_or_default();
+                let embeddings = step
+                    .attachments
+                    .iter()
+                    .filter_map(|path| {
+                        let content = std::fs::read(path)
+                            .map_err(|err| {
+                                #struct_name_ident::__warn(&format!(
+                                    "Failed to embed the artifact {}: {err}",
+                                    path.display(),
+                                ));
+                            })
+                            .ok()?;
+                        Some(format!(
+                            "{{\"mime_type\": \"{}\", \"name\": {}, \"data\": \"{}\"}}",
+                            #struct_name_ident::__mime_type(path),
+                            json(&path.file_name().unwrap_or_default().to_string_lossy()),
+                            #struct_name_ident::__base64_encode(&content),
+                        ))
+                    })
+                    .collect::<Vec<_>>();
+                let step_fields = if step.section == "before" || step.section == "after" {
+                    String::new()
+                } else {
+                    format!(
+                        "\"keyword\": {}, \"name\": {}, \"line\": {}, ",
+                        json(&step.keyword),
+                        json(&step.name),
+                        step.line,
+                    )
+                };
+                format!(
+                    "{{{step_fields}\"result\": {{\"status\": \"{}\", \"duration\": {}{error}}}, \"embeddings\": [{}]}}",
+                    step.status,
+                    step.duration.as_nanos(),
+                    embeddings.join(", "),
+                )
+            }
+
+            fn __record(&mut self, event: &#cucumber::event::Cucumber<#struct_name_ident>) {
+                use #cucumber::event::{Cucumber, Feature, Rule};
+
+                match event {
+                    Cucumber::Feature(feature, Feature::Started) => self.features.push(#feature_ident {
+                        keyword: feature.keyword.clone(),
+                        name: feature.name.clone(),
+                        description: feature.description.clone().unwrap_or_default(),
+                        path: feature.path.clone(),
+                        line: feature.position.line,
+                        tags: feature.tags.clone(),
+                        scenarios: vec![],
+                    }),
+                    Cucumber::Feature(feature, Feature::Scenario(scenario, event)) => {
+                        self.__record_scenario(feature, None, scenario, &event.event);
+                    }
+                    Cucumber::Feature(feature, Feature::Rule(rule, Rule::Scenario(scenario, event))) => {
+                        self.__record_scenario(feature, Some(rule), scenario, &event.event);
+                    }
+                    _ => {}
+                }
+            }
+
+            fn __record_scenario(
+                &mut self,
+                feature: &#cucumber::gherkin::Feature,
+                rule: Option<&#cucumber::gherkin::Rule>,
+                scenario: &#cucumber::gherkin::Scenario,
+                event: &#cucumber::event::Scenario<#struct_name_ident>,
+            ) {
+                use #cucumber::event::{Hook, HookType, Scenario, Step};
+
+                let dir = #struct_name_ident::__scenario_dir(
+                    self.artifacts_dir.get_or_insert_with(#struct_name_ident::__discover_artifacts_dir),
+                    &feature.name,
+                    scenario,
+                );
+                let Some(report_feature) = self
+                    .features
+                    .iter_mut()
+                    .rev()
+                    .find(|report_feature| report_feature.name == feature.name && report_feature.path == feature.path)
+                else {
+                    return;
+                };
+                if let Scenario::Started = event {
+                    // Retried scenarios start over.
+                    report_feature.scenarios.retain(|report_scenario| report_scenario.line != scenario.position.line);
+                    report_feature.scenarios.push(#scenario_ident {
+                        keyword: scenario.keyword.clone(),
+                        name: scenario.name.clone(),
+                        description: scenario.description.clone().unwrap_or_default(),
+                        rule: rule.map(|rule| rule.name.clone()),
+                        line: scenario.position.line,
+                        tags: scenario.tags.clone(),
+                        parameters: Self::__example_parameters(scenario),
+                        started: std::time::SystemTime::now(),
+                        duration: std::time::Duration::ZERO,
+                        steps: vec![],
+                    });
+                    return;
+                }
+                let Some(report_scenario) = report_feature
+                    .scenarios
+                    .iter_mut()
+                    .rev()
+                    .find(|report_scenario| report_scenario.line == scenario.position.line)
+                else {
+                    return;
+                };
+
+                let steps = &mut report_scenario.steps;
+                let (section, step, event) = match event {
+                    Scenario::Hook(ty, hook) => {
+                        let (section, keyword) = match ty {
+                            HookType::Before => ("before", "Before"),
+                            HookType::After => ("after", "After"),
+                        };
+                        match hook {
+                            Hook::Started => steps.push(#step_ident::__start(section, keyword.to_string(), String::new(), 0)),
+                            Hook::Passed => {
+                                if let Some(step) = steps.last_mut() {
+                                    step.__finish("passed", None);
+                                }
+                            }
+                            Hook::Failed(_, info) => {
+                                if let Some(step) = steps.last_mut() {
+                                    let message = info
+                                        .downcast_ref::<String>()
+                                        .cloned()
+                                        .or_else(|| info.downcast_ref::<&str>().map(ToString::to_string))
+                                        .unwrap_or_else(|| "Hook panicked".to_string());
+                                    step.__finish("failed", Some(message));
+                                    step.attachments.extend(#struct_name_ident::__take_artifacts(&dir));
+                                }
+                            }
+                        }
+                        match (matches!(hook, Hook::Started), steps.last()) {
+                            (true, Some(step)) => #struct_name_ident::__command_log_step(&dir, step),
+                            (false, Some(step)) => #struct_name_ident::__trace_step(&dir, step),
+                            _ => {}
+                        }
+                        return;
+                    }
+                    Scenario::Background(step, event) => ("background", step, event),
+                    Scenario::Step(step, event) => ("scenario", step, event),
+                    Scenario::Finished => {
+                        report_scenario.duration = report_scenario.started.elapsed().unwrap_or_default();
+                        #struct_name_ident::__save_trace(&dir, &feature.name, report_scenario);
+                        #struct_name_ident::__finish_command_log(&dir);
+                        // Artifacts saved after the steps, like the console logs, the trace
+                        // and the command log, go to the failed step or to the last one.
+                        let artifacts = #struct_name_ident::__take_artifacts(&dir);
+                        let steps = &mut report_scenario.steps;
+                        let index = steps
+                            .iter()
+                            .rposition(|step| step.status == "failed")
+                            .or(steps.len().checked_sub(1));
+                        if let Some(index) = index {
+                            steps[index].attachments.extend(artifacts);
+                        }
+                        if let Some(results_dir) = #struct_name_ident::__discover_allure_results() {
+                            Self::__save_allure_result(&results_dir, &feature.name, &feature.tags, report_scenario);
+                        }
+                        return;
+                    }
+                    _ => return,
+                };
+                match event {
+                    Step::Started => steps.push(#step_ident::__start(
+                        section,
+                        step.keyword.clone(),
+                        step.value.clone(),
+                        step.position.line,
+                    )),
+                    Step::Skipped => {
+                        if let Some(step) = steps.last_mut() {
+                            step.__finish("skipped", None);
+                        }
+                    }
+                    Step::Passed(..) => {
+                        if let Some(step) = steps.last_mut() {
+                            step.__finish("passed", None);
+                        }
+                    }
+                    Step::Failed(.., err) => {
+                        if let Some(step) = steps.last_mut() {
+                            step.__finish("failed", Some(err.to_string()));
+                            step.attachments.extend(#struct_name_ident::__take_artifacts(&dir));
+                        }
+                    }
+                }
+                match (matches!(event, Step::Started), steps.last()) {
+                    (true, Some(step)) => #struct_name_ident::__command_log_step(&dir, step),
+                    (false, Some(step)) => #struct_name_ident::__trace_step(&dir, step),
+                    _ => {}
+                }
+            }
+
+            // The scenarios of an outline are expanded by cucumber with the line of their
+            // row of the examples, which is used to find the values of the row.
+            fn __example_parameters(scenario: &#cucumber::gherkin::Scenario) -> Vec<(String, String)> {
+                scenario
+                    .examples
+                    .iter()
+                    .filter(|examples| examples.position.line < scenario.position.line)
+                    .max_by_key(|examples| examples.position.line)
+                    .and_then(|examples| {
+                        let rows = &examples.table.as_ref()?.rows;
+                        // The first row is the header with the names of the parameters.
+                        let index = scenario.position.line - examples.position.line - 1;
+                        let row = rows.get(index).filter(|_| index > 0)?;
+                        Some(rows.first()?.iter().cloned().zip(row.iter().cloned()).collect())
+                    })
+                    .unwrap_or_default()
+            }
+
+            fn __save(&self) {
+                if let Some(path) = #struct_name_ident::__discover_json_report() {
+                    #struct_name_ident::__save_report(&path, self.to_cucumber_json().as_bytes());
+                }
+                if let Some(path) = #struct_name_ident::__discover_html_report() {
+                    #struct_name_ident::__save_report(&path, self.to_html().as_bytes());
+                }
+                if let Some(path) = #struct_name_ident::__discover_junit_report() {
+                    #struct_name_ident::__save_report(&path, self.to_junit_xml().as_bytes());
+                }
+                if let Some(results_dir) = #struct_name_ident::__discover_allure_results() {
+                    Self::__save_allure_environment(&results_dir);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn build_report_fns() -> TokenStream {
+    quote! {
+        // Path of a report enabled by the given environment variable, with the name of
+        // the browser added before the extension when running a browser matrix.
+        fn __report_path(name: &str) -> Option<std::path::PathBuf> {
+            let path = std::path::PathBuf::from(
+                Self::__config_value(name).filter(|value| !value.trim().is_empty())?.trim(),
+            );
+            let browser = Self::__matrix_browser()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .clone();
+            let Some(browser) = browser else {
+                return Some(path);
+            };
+            let mut file_name = path.file_stem().unwrap_or_default().to_os_string();
+            file_name.push(format!(".{browser}"));
+            if let Some(extension) = path.extension() {
+                file_name.push(".");
+                file_name.push(extension);
+            }
+            Some(path.with_file_name(file_name))
+        }
+
+        fn __save_report(path: &std::path::Path, content: &[u8]) {
+            let saved = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => std::fs::create_dir_all(dir),
+                _ => Ok(()),
+            }
+            .and_then(|()| std::fs::write(path, content));
+            match saved {
+                Ok(()) => Self::__info(&format!("Report saved to {}", path.display())),
+                Err(err) => Self::__warn(&format!("Failed to save the report {}: {err}", path.display())),
+            }
+        }
+
+        fn __mime_type(path: &std::path::Path) -> &'static str {
+            match path.extension().and_then(std::ffi::OsStr::to_str) {
+                Some("png") => "image/png",
+                Some("gif") => "image/gif",
+                Some("webp") => "image/webp",
+                Some("html") => "text/html",
+                Some("json") => "application/json",
+                Some("zip") => "application/zip",
+                Some("log" | "txt") => "text/plain",
+                _ => "application/octet-stream",
+            }
+        }
+
+        fn __discover_json_report() -> Option<std::path::PathBuf> {
+            Self::__report_path("JSON_REPORT")
+        }
+    }
+}
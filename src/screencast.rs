@@ -0,0 +1,252 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Maximum number of frames kept in memory for a screencast.
+const MAX_SCREENCAST_FRAMES: usize = 120;
+
+pub(crate) fn build_screencast_fns(cucumber: &syn::Path, thirtyfour: &syn::Path) -> TokenStream {
+    let fps_doc = format!(
+        "lower when the browser takes longer to take them. At most {MAX_SCREENCAST_FRAMES} frames are kept,"
+    );
+
+    quote! {
+        #[doc = "Get when a screencast of the scenarios is saved."]
+        #[doc = ""]
+        #[doc = "It's defined by the `SCREENCAST` environment variable, one of `\"off\"` (default),"]
+        #[doc = "`\"on-failure\"` or `\"always\"`. When enabled, screenshots are taken in the"]
+        #[doc = "background during the scenario and saved as an animated PNG to"]
+        #[doc = "`ARTIFACTS_DIR/<feature>/<scenario>/screencast.png`."]
+        #[must_use]
+        pub fn screencast(&self) -> &str {
+            &self.screencast
+        }
+
+        #[doc = "Get the number of screenshots taken per second for the screencasts."]
+        #[doc = ""]
+        #[doc = "It's defined by the `SCREENCAST_FPS` environment variable, which defaults to `2`."]
+        #[doc = "Screenshots identical to the previous one are skipped, and the frame rate may be"]
+        #[doc = #fps_doc]
+        #[doc = "so the frame rate is halved each time a screencast reaches them."]
+        #[must_use]
+        pub fn screencast_fps(&self) -> f64 {
+            self.screencast_fps
+        }
+
+        // Screenshots of the running screencasts with the time they were taken, by their
+        // session, along with the number of screenshots taken per frame kept.
+        fn __screencasts() -> &'static std::sync::Mutex<
+            std::collections::BTreeMap<String, (u32, Vec<(std::time::Instant, Vec<u8>)>)>,
+        > {
+            static SCREENCASTS: std::sync::Mutex<
+                std::collections::BTreeMap<String, (u32, Vec<(std::time::Instant, Vec<u8>)>)>,
+            > = std::sync::Mutex::new(std::collections::BTreeMap::new());
+            &SCREENCASTS
+        }
+
+        // Add a screenshot to a screencast, returning the number of screenshots taken per
+        // frame kept while it's running. Every other frame is dropped when there are too
+        // many of them, so the memory used by long scenarios is bounded.
+        fn __push_screencast_frame(session_id: &str, frame: Option<Vec<u8>>) -> Option<u32> {
+            let mut screencasts = Self::__screencasts().lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+            let (stride, frames) = screencasts.get_mut(session_id)?;
+            if let Some(frame) = frame.filter(|frame| frames.last().is_none_or(|(_, last)| last != frame)) {
+                if frames.len() >= #MAX_SCREENCAST_FRAMES {
+                    let mut index = 0;
+                    frames.retain(|_| {
+                        index += 1;
+                        index % 2 == 1
+                    });
+                    *stride *= 2;
+                }
+                frames.push((std::time::Instant::now(), frame));
+            }
+            Some(*stride)
+        }
+
+        // Take screenshots in a background task until the screencast is stopped. The task
+        // runs in a thread of its own, so its commands aren't recorded in the traces.
+        fn __start_screencast(&self) {
+            if self.screencast == "off" {
+                return;
+            }
+            let session_id = self.driver().session_id().to_string();
+            Self::__screencasts()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .insert(session_id.clone(), (1, vec![]));
+
+            // The task doesn't keep the session alive, which is closed when the world is dropped.
+            let handle = std::sync::Arc::downgrade(&self.driver().handle);
+            let interval = std::time::Duration::from_secs_f64(1.0 / self.screencast_fps);
+            std::thread::spawn(move || {
+                #thirtyfour::support::block_on(async move {
+                    let mut stride = 1;
+                    loop {
+                        let started = std::time::Instant::now();
+                        let Some(handle) = handle.upgrade() else {
+                            return;
+                        };
+                        let frame = handle.screenshot_as_png().await.ok();
+                        drop(handle);
+                        let Some(current) = Self::__push_screencast_frame(&session_id, frame) else {
+                            return;
+                        };
+                        stride = current;
+                        #thirtyfour::support::sleep((interval * stride).saturating_sub(started.elapsed())).await;
+                    }
+                });
+            });
+        }
+
+        // Stop the screencast of a finished scenario and save it when the policy requires it.
+        async fn __stop_screencast(&self, feature: &str, scenario: &#cucumber::gherkin::Scenario, failed: bool) {
+            if self.screencast == "off" {
+                return;
+            }
+            let session_id = self.driver().session_id().to_string();
+            // The last state of the page is likely missed by the background task.
+            if let Ok(screenshot) = self.driver().screenshot_as_png().await {
+                Self::__push_screencast_frame(&session_id, Some(screenshot));
+            }
+            let frames = Self::__screencasts()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .remove(&session_id);
+            let Some((_, frames)) = frames else {
+                return;
+            };
+            if self.screencast != "always" && !failed {
+                return;
+            }
+
+            let last_delay = std::time::Duration::from_secs_f64(1.0 / self.screencast_fps);
+            let frames = frames
+                .iter()
+                .enumerate()
+                .map(|(index, (taken, frame))| {
+                    let delay = frames
+                        .get(index + 1)
+                        .map_or(last_delay, |(next, _)| next.saturating_duration_since(*taken));
+                    (delay, frame.as_slice())
+                })
+                .collect::<Vec<_>>();
+            let Some(screencast) = Self::__animated_png(&frames) else {
+                Self::__warn(&format!(
+                    "No screenshots to save the screencast of the scenario \"{}\"",
+                    scenario.name,
+                ));
+                return;
+            };
+            let dir = self.__scenario_artifacts_dir(feature, scenario);
+            if let Err(err) = std::fs::create_dir_all(&dir) {
+                Self::__warn(&format!("Failed to create the artifacts directory {}: {err}", dir.display()));
+                return;
+            }
+            Self::__write_artifact(&dir.join("screencast.png"), &screencast);
+        }
+
+        // Animated PNG of the screenshots, reusing their compressed image data as is for
+        // the frames. Screenshots of another size than the first one, like after resizing
+        // the window, are dropped.
+        fn __animated_png(frames: &[(std::time::Duration, &[u8])]) -> Option<Vec<u8>> {
+            let frames = frames
+                .iter()
+                .filter_map(|(delay, png)| Some((*delay, Self::__png_chunks(png)?)))
+                .collect::<Vec<_>>();
+            let header = |chunks: &[([u8; 4], &[u8])]| {
+                chunks.iter().find(|(kind, _)| kind == b"IHDR").map(|(_, data)| data.to_vec())
+            };
+            let first_header = header(&frames.first()?.1).filter(|header| header.len() == 13)?;
+            let frames = frames
+                .iter()
+                .filter(|(_, chunks)| header(chunks).as_ref() == Some(&first_header))
+                .collect::<Vec<_>>();
+
+            let write_chunk = |png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]| {
+                png.extend_from_slice(&u32::try_from(data.len()).unwrap_or(u32::MAX).to_be_bytes());
+                let start = png.len();
+                png.extend_from_slice(kind);
+                png.extend_from_slice(data);
+                let crc = Self::__crc32(&png[start..]);
+                png.extend_from_slice(&crc.to_be_bytes());
+            };
+            let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+            write_chunk(&mut png, b"IHDR", &first_header);
+            let mut animation_control = u32::try_from(frames.len()).unwrap_or(u32::MAX).to_be_bytes().to_vec();
+            // Played in a loop.
+            animation_control.extend_from_slice(&0u32.to_be_bytes());
+            write_chunk(&mut png, b"acTL", &animation_control);
+            // Chunks preceding the image data of the first screenshot, like the palette or
+            // the color space.
+            for (kind, data) in frames[0].1.iter().take_while(|(kind, _)| kind != b"IDAT") {
+                if !matches!(kind, b"IHDR" | b"acTL" | b"fcTL" | b"fdAT") {
+                    write_chunk(&mut png, kind, data);
+                }
+            }
+
+            let mut sequence = 0u32;
+            for (index, (delay, chunks)) in frames.iter().enumerate() {
+                let mut frame_control = sequence.to_be_bytes().to_vec();
+                // Size of the frame, then its offset.
+                frame_control.extend_from_slice(&first_header[..8]);
+                frame_control.extend_from_slice(&[0; 8]);
+                // Delay in milliseconds.
+                frame_control.extend_from_slice(&u16::try_from(delay.as_millis()).unwrap_or(u16::MAX).to_be_bytes());
+                frame_control.extend_from_slice(&1000u16.to_be_bytes());
+                // No disposal and no blending, the frames being whole screenshots.
+                frame_control.extend_from_slice(&[0, 0]);
+                write_chunk(&mut png, b"fcTL", &frame_control);
+                sequence += 1;
+                for (_, data) in chunks.iter().filter(|(kind, _)| kind == b"IDAT") {
+                    if index == 0 {
+                        write_chunk(&mut png, b"IDAT", data);
+                    } else {
+                        let mut frame_data = sequence.to_be_bytes().to_vec();
+                        frame_data.extend_from_slice(data);
+                        write_chunk(&mut png, b"fdAT", &frame_data);
+                        sequence += 1;
+                    }
+                }
+            }
+            write_chunk(&mut png, b"IEND", &[]);
+            Some(png)
+        }
+
+        fn __png_chunks(png: &[u8]) -> Option<Vec<([u8; 4], &[u8])>> {
+            let mut rest = png.strip_prefix(b"\x89PNG\r\n\x1a\n")?;
+            let mut chunks = vec![];
+            while rest.len() >= 12 {
+                let length = usize::try_from(u32::from_be_bytes(rest[..4].try_into().ok()?)).ok()?;
+                chunks.push((rest[4..8].try_into().ok()?, rest.get(8..8 + length)?));
+                rest = rest.get(12 + length..)?;
+            }
+            Some(chunks)
+        }
+
+        fn __discover_screencast() -> Result<String, String> {
+            let screencast = Self::__config_value("SCREENCAST")
+                .map_or("off".to_string(), |value| value.trim().to_ascii_lowercase());
+            if !matches!(screencast.as_str(), "off" | "on-failure" | "always") {
+                return Err(format!(
+                    "Invalid SCREENCAST environment variable value \"{screencast}\". \
+                    Expected one of: \"off\", \"on-failure\" or \"always\"."
+                ));
+            }
+            Ok(screencast)
+        }
+
+        fn __discover_screencast_fps() -> Result<f64, String> {
+            let Some(value) = Self::__config_value("SCREENCAST_FPS") else {
+                return Ok(2.0);
+            };
+            match value.trim().parse::<f64>() {
+                Ok(fps) if fps > 0.0 && fps <= 30.0 => Ok(fps),
+                _ => Err(format!(
+                    "Invalid SCREENCAST_FPS environment variable value \"{value}\". \
+                    Expected a number of screenshots per second greater than 0 and up to 30, \
+                    like \"2\" or \"0.5\"."
+                )),
+            }
+        }
+    }
+}
@@ -1,9 +1,10 @@
+use crate::WorlderArgs;
 use std::{env, path::Path};
 
 fn extract_version(content: &str, match_: &str) -> Option<String> {
     for line in content.lines() {
         if line.starts_with(match_) {
-            return line.split('"').nth(1).map(|s| s.to_string());
+            return line.split('"').nth(1).map(ToString::to_string);
         }
     }
     None
@@ -96,3 +97,103 @@ fn thirtyfour_version_in_readme_is_updated_with_docref() {
         "Thirtyfour version in src/lib.rs does not match docref/Cargo.toml"
     );
 }
+
+#[test]
+fn env_file_argument_is_parsed() {
+    let args = syn::parse_str::<WorlderArgs>(r#"env_file = ".env.e2e""#)
+        .expect("Failed to parse #[worlder] arguments");
+    assert_eq!(
+        args.env_file.map(|path| path.value()).as_deref(),
+        Some(".env.e2e")
+    );
+
+    let args = syn::parse_str::<WorlderArgs>("").expect("Failed to parse #[worlder] arguments");
+    assert!(args.env_file.is_none());
+}
+
+#[test]
+fn hydration_argument_is_validated() {
+    for hydration in [
+        "wasm",
+        "trunk",
+        "leptos",
+        "yew",
+        "dioxus",
+        "window.hydrated === true",
+    ] {
+        let args = syn::parse_str::<WorlderArgs>(&format!("hydration = {hydration:?}"))
+            .expect("Failed to parse #[worlder] arguments");
+        assert_eq!(
+            args.hydration.map(|hydration| hydration.value()).as_deref(),
+            Some(hydration)
+        );
+    }
+
+    assert!(syn::parse_str::<WorlderArgs>(r#"hydration = "sycamore""#).is_err());
+}
+
+#[test]
+fn test_id_attribute_argument_is_validated() {
+    let args = syn::parse_str::<WorlderArgs>(r#"test_id_attribute = "data-qa""#)
+        .expect("Failed to parse #[worlder] arguments");
+    assert_eq!(args.test_id_attribute.value(), "data-qa");
+
+    let args = syn::parse_str::<WorlderArgs>("").expect("Failed to parse #[worlder] arguments");
+    assert_eq!(args.test_id_attribute.value(), "data-testid");
+
+    assert!(syn::parse_str::<WorlderArgs>(r#"test_id_attribute = "data qa]""#).is_err());
+}
+
+#[test]
+fn tracing_argument_is_parsed() {
+    let args = syn::parse_str::<WorlderArgs>("tracing = ::tracing")
+        .expect("Failed to parse #[worlder] arguments");
+    let tracing = args.tracing.expect("tracing argument not parsed");
+    assert_eq!(quote::quote!(#tracing).to_string(), ":: tracing");
+
+    let args = syn::parse_str::<WorlderArgs>("").expect("Failed to parse #[worlder] arguments");
+    assert!(args.tracing.is_none());
+}
+
+#[test]
+fn page_derive_requires_valid_locators() {
+    let derive = |source: &str| {
+        let input = syn::parse_str::<syn::DeriveInput>(source).expect("Failed to parse struct");
+        crate::page::derive_page(&input)
+    };
+
+    assert!(
+        derive(
+            r#"#[page(path = "/login", world = AppWorld)]
+        struct LoginPage { #[locator(css = "input")] email: By }"#
+        )
+        .is_ok()
+    );
+    assert!(derive(r#"struct LoginPage { #[locator(css = "input")] email: By }"#).is_err());
+    assert!(derive(r#"#[page(path = "login", world = AppWorld)] struct LoginPage;"#).is_err());
+    assert!(
+        derive(
+            r#"#[page(path = "/login", world = AppWorld)]
+        struct LoginPage { #[locator(role = "button", name = "Log in")] submit: AppWorldLocator }"#
+        )
+        .is_ok()
+    );
+    assert!(
+        derive(
+            r#"#[page(path = "/login", world = AppWorld)]
+        struct LoginPage { #[locator(role = "button", label = "Log in")] submit: AppWorldLocator }"#
+        )
+        .is_err()
+    );
+    assert!(
+        derive(
+            r#"#[page(path = "/login", world = AppWorld)]
+        struct LoginPage { #[locator(aria = "button")] submit: AppWorldLocator }"#
+        )
+        .is_err()
+    );
+    assert!(
+        derive(r#"#[page(path = "/login", world = AppWorld)] struct LoginPage { email: By }"#)
+            .is_err()
+    );
+}
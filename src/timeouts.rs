@@ -0,0 +1,87 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+pub(crate) fn build_timeouts_fns(thirtyfour: &syn::Path) -> TokenStream {
+    quote! {
+        #[doc = "Get the page load timeout of the world."]
+        #[doc = ""]
+        #[doc = "It's defined by the `PAGE_LOAD_TIMEOUT` environment variable, which defaults to `300s`."]
+        #[must_use]
+        pub fn page_load_timeout(&self) -> std::time::Duration {
+            self.page_load_timeout
+        }
+
+        #[doc = "Get the script timeout of the world."]
+        #[doc = ""]
+        #[doc = "It's defined by the `SCRIPT_TIMEOUT` environment variable, which defaults to `30s`."]
+        #[must_use]
+        pub fn script_timeout(&self) -> std::time::Duration {
+            self.script_timeout
+        }
+
+        #[doc = "Get the implicit wait timeout of the world."]
+        #[doc = ""]
+        #[doc = "It's defined by the `IMPLICIT_WAIT` environment variable, which defaults to `0s`."]
+        #[must_use]
+        pub fn implicit_wait(&self) -> std::time::Duration {
+            self.implicit_wait
+        }
+
+        #[doc = "Get the page load strategy of the world."]
+        #[doc = ""]
+        #[doc = "It's defined by the `PAGE_LOAD_STRATEGY` environment variable, which defaults to `\"normal\"`."]
+        #[must_use]
+        pub fn page_load_strategy(&self) -> &str {
+            &self.page_load_strategy
+        }
+
+        fn __discover_timeouts(
+        ) -> Result<(std::time::Duration, std::time::Duration, std::time::Duration), String> {
+            let discover = |name: &str, default: std::time::Duration| {
+                Self::__config_value(name)
+                    .map_or(Ok(default), |value| Self::__parse_duration(name, &value))
+            };
+            Ok((
+                discover("PAGE_LOAD_TIMEOUT", std::time::Duration::from_secs(300))?,
+                discover("SCRIPT_TIMEOUT", std::time::Duration::from_secs(30))?,
+                discover("IMPLICIT_WAIT", std::time::Duration::ZERO)?,
+            ))
+        }
+
+        fn __discover_page_load_strategy() -> Result<String, String> {
+            let strategy = Self::__config_value("PAGE_LOAD_STRATEGY")
+                .map_or("normal".to_string(), |value| value.trim().to_ascii_lowercase());
+            if !matches!(strategy.as_str(), "normal" | "eager" | "none") {
+                return Err(format!(
+                    "Invalid PAGE_LOAD_STRATEGY environment variable value \"{strategy}\". \
+                    Expected one of: \"normal\", \"eager\" or \"none\"."
+                ));
+            }
+            Ok(strategy)
+        }
+
+        fn __set_session_capabilities<C: #thirtyfour::CapabilitiesHelper>(
+            caps: &mut C,
+            (page_load_timeout, script_timeout, implicit_wait): (
+                std::time::Duration,
+                std::time::Duration,
+                std::time::Duration,
+            ),
+            page_load_strategy: &str,
+        ) -> Result<(), String> {
+            let millis = |duration: std::time::Duration| {
+                u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+            };
+            let timeouts = std::collections::HashMap::from([
+                ("pageLoad", millis(page_load_timeout)),
+                ("script", millis(script_timeout)),
+                ("implicit", millis(implicit_wait)),
+            ]);
+            caps.set_base_capability("timeouts", timeouts)
+                .map_err(|err| format!("Failed to set timeouts capability: {err}"))?;
+            caps.set_base_capability("pageLoadStrategy", page_load_strategy)
+                .map_err(|err| format!("Failed to set pageLoadStrategy capability: {err}"))?;
+            Ok(())
+        }
+    }
+}
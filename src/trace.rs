@@ -0,0 +1,649 @@
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use quote::{format_ident, quote};
+
+/// Static viewer of the traces, `/*TRACE*/` being replaced by the JSON of the
+/// trace so it works when opened from the file system.
+const VIEWER: &str = r#"<!DOCTYPE html>
+<html lang="en"><head><meta charset="utf-8"><title>Trace</title><style>
+body { margin: 0; font-family: system-ui, sans-serif; color: #1f2328; display: grid; grid-template-columns: 24em 1fr; grid-template-rows: auto 1fr; height: 100vh; }
+header { grid-column: 1 / 3; padding: .5em 1em; border-bottom: 1px solid #d0d7de; background: #f6f8fa; }
+h1 { font-size: 1.2em; margin: .2em 0 .5em; }
+header input { width: 100%; }
+nav { overflow: auto; border-right: 1px solid #d0d7de; }
+nav button { display: block; width: 100%; text-align: left; border: 0; border-bottom: 1px solid #eaeef2; background: none; padding: .5em; cursor: pointer; font: inherit; }
+nav button.selected { background: #ddf4ff; }
+main { overflow: auto; padding: 0 1em 1em; }
+.passed { color: #1a7f37; } .failed { color: #cf222e; } .skipped { color: #9a6700; }
+.muted { color: #656d76; font-size: .85em; }
+img { max-width: 100%; border: 1px solid #d0d7de; }
+table { border-collapse: collapse; font-size: .85em; width: 100%; margin: 1em 0; }
+td, th { border-bottom: 1px solid #eaeef2; padding: .2em .5em; text-align: left; vertical-align: top; }
+td.payload { font-family: monospace; word-break: break-all; }
+pre { white-space: pre-wrap; background: #f6f8fa; padding: .5em; }
+</style></head><body>
+<header><h1 id="title"></h1><input id="timeline" type="range" min="0" value="0" aria-label="Timeline"></header>
+<nav id="steps"></nav><main id="step"></main>
+<script>
+const trace = /*TRACE*/;
+const escape = (value) => String(value ?? '').replace(/[&<>"']/g, (c) => `&#${c.charCodeAt(0)};`);
+const start = trace.steps.length ? trace.steps[0].started : 0;
+const buttons = trace.steps.map((step, index) => {
+    const button = document.createElement('button');
+    button.innerHTML = `<span class="${step.status}">&#9679;</span> ${escape(step.keyword)}${escape(step.name)} <span class="muted">${step.duration.toFixed(0)} ms</span>`;
+    button.addEventListener('click', () => select(index));
+    document.getElementById('steps').append(button);
+    return button;
+});
+const timeline = document.getElementById('timeline');
+timeline.max = Math.max(trace.steps.length - 1, 0);
+timeline.addEventListener('input', () => select(Number(timeline.value)));
+document.addEventListener('keydown', (event) => {
+    const index = Number(timeline.value) + (event.key === 'ArrowDown' || event.key === 'ArrowRight' ? 1 : event.key === 'ArrowUp' || event.key === 'ArrowLeft' ? -1 : 0);
+    if (index >= 0 && index < trace.steps.length) select(index);
+});
+document.getElementById('title').innerHTML = `<span class="${trace.status}">${escape(trace.status)}</span> ${escape(trace.feature)} &rsaquo; ${escape(trace.scenario)}`;
+const select = (index) => {
+    const step = trace.steps[index];
+    timeline.value = index;
+    buttons.forEach((button, other) => button.classList.toggle('selected', other === index));
+    const commands = step.commands.map((command) => `<tr><td>${escape(command.method)}</td><td>${escape(command.endpoint)}</td><td class="payload">${escape(command.payload)}</td><td>${command.duration.toFixed(1)} ms</td><td class="${command.error ? 'failed' : 'passed'}">${command.status}${command.error ? ' ' + escape(command.error) : ''}</td></tr>`).join('');
+    document.getElementById('step').innerHTML = `<h2 class="${step.status}">${escape(step.keyword)}${escape(step.name)}</h2>
+        <p class="muted">${escape(step.status)} &middot; ${step.duration.toFixed(0)} ms &middot; +${(step.started - start).toFixed(0)} ms</p>
+        <p>URL: ${step.url ? `<a href="${escape(step.url)}">${escape(step.url)}</a>` : 'unknown'}</p>
+        ${step.error ? `<pre class="failed">${escape(step.error)}</pre>` : ''}
+        ${step.screenshot ? `<img alt="Screenshot after the step" src="${escape(step.screenshot)}">` : ''}
+        <table><thead><tr><th>Method</th><th>Endpoint</th><th>Payload</th><th>Duration</th><th>Status</th></tr></thead><tbody>${commands || '<tr><td colspan="5">No commands</td></tr>'}</tbody></table>`;
+};
+if (trace.steps.length) select(trace.steps.findIndex((step) => step.status === 'failed') >= 0 ? trace.steps.findIndex((step) => step.status === 'failed') : 0);
+</script></body></html>
+"#;
+
+pub(crate) fn build_command_struct(vis_ident: &TokenTree, command_ident: &Ident) -> TokenStream {
+    quote! {
+        #[doc = "WebDriver command sent by a world, recorded from the events of `thirtyfour`."]
+        #[derive(Debug, Clone)]
+        #vis_ident struct #command_ident {
+            #[doc = "Session of the driver that sent the command, empty for the commands that are not"]
+            #[doc = "sent to a session, like the one creating it."]
+            pub session_id: String,
+            #[doc = "HTTP method of the command."]
+            pub method: String,
+            #[doc = "Endpoint of the command relative to the session, like `/url`, or the whole path"]
+            #[doc = "for the commands that are not sent to a session."]
+            pub endpoint: String,
+            #[doc = "Summary of the payload of the command, redacted by the hooks added with"]
+            #[doc = "`redact_snapshots_with`."]
+            pub payload: String,
+            #[doc = "Time when the command was sent."]
+            pub started: std::time::SystemTime,
+            #[doc = "Time the driver took to respond."]
+            pub duration: std::time::Duration,
+            #[doc = "HTTP status of the response, `0` when no response was received."]
+            pub status: u16,
+            #[doc = "WebDriver error of the response, if any."]
+            pub error: Option<String>,
+        }
+    }
+}
+
+/// Recording of the commands sent by `thirtyfour`, which has no other hook to
+/// observe them than its `tracing` events, so it's only possible when the
+/// `tracing` crate is passed to the macro.
+fn build_record_commands(
+    tracing: Option<&syn::Path>,
+    struct_name_ident: &TokenTree,
+    command_ident: &Ident,
+) -> TokenStream {
+    let Some(tracing) = tracing else {
+        return quote! {
+            fn __record_commands() {}
+        };
+    };
+
+    quote! {
+        // Record the commands of the traced scenarios and log the commands from the debug
+        // events of the `run_webdriver_cmd` span in which `thirtyfour` sends each command.
+        // The recorder is made the default subscriber of this thread, which polls the
+        // runner, and forwards everything to the subscriber of the user.
+        fn __record_commands() {
+            use std::sync::atomic::{AtomicU64, Ordering};
+            use #tracing::{Event, Metadata, span, subscriber::Interest};
+
+            const TARGET: &str = "thirtyfour::session::http";
+            // Spans of the commands not enabled by the subscriber of the user get ids
+            // of their own, with the highest bit set.
+            const OWN_SPAN: u64 = 1 << 63;
+
+            thread_local! {
+                static ENTERED: std::cell::RefCell<Vec<u64>> = const { std::cell::RefCell::new(vec![]) };
+                static RECORDER: std::cell::RefCell<Option<#tracing::dispatcher::DefaultGuard>> =
+                    const { std::cell::RefCell::new(None) };
+            }
+
+            struct Message(String);
+
+            impl #tracing::field::Visit for Message {
+                fn record_debug(&mut self, field: &#tracing::field::Field, value: &dyn std::fmt::Debug) {
+                    if field.name() == "message" {
+                        self.0 = format!("{value:?}");
+                    }
+                }
+            }
+
+            struct CommandRecorder {
+                inner: #tracing::Dispatch,
+                // Commands being sent by their span, with the instant they were sent.
+                commands: std::sync::Mutex<
+                    std::collections::BTreeMap<u64, Option<(#command_ident, std::time::Instant)>>,
+                >,
+                next_id: AtomicU64,
+                trace: bool,
+                command_log: String,
+            }
+
+            impl CommandRecorder {
+                fn record_event(&self, event: &Event<'_>) {
+                    let mut message = Message(String::new());
+                    event.record(&mut message);
+                    let Some(span) = ENTERED.with(|entered| entered.borrow().last().copied()) else {
+                        return;
+                    };
+                    let mut commands = self.commands.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+                    let Some(command) = commands.get_mut(&span) else {
+                        return;
+                    };
+                    if let Some(request) = message.0.strip_prefix("webdriver request: ") {
+                        let (method, rest) = request.split_once(' ').unwrap_or((request, ""));
+                        let (uri, payload) = rest.split_once(' ').unwrap_or((rest, ""));
+                        // The commands creating the sessions are not part of any.
+                        let Some(path) = uri.trim_start_matches('/').strip_prefix("session/") else {
+                            return;
+                        };
+                        let (session_id, endpoint) = path.find('/').map_or((path, ""), |index| path.split_at(index));
+                        let mut payload = #struct_name_ident::__redact("command", payload);
+                        if payload.len() > 200 {
+                            let mut end = 200;
+                            while !payload.is_char_boundary(end) {
+                                end -= 1;
+                            }
+                            payload.truncate(end);
+                            payload.push('…');
+                        }
+                        let sent = #command_ident {
+                            session_id: session_id.to_string(),
+                            method: method.to_string(),
+                            endpoint: endpoint.to_string(),
+                            payload,
+                            started: std::time::SystemTime::now(),
+                            duration: std::time::Duration::ZERO,
+                            status: 0,
+                            error: None,
+                        };
+                        *command = Some((sent.clone(), std::time::Instant::now()));
+                        drop(commands);
+                        self.log(&sent, true);
+                    } else if let Some(response) = message.0.strip_prefix("webdriver response: ") {
+                        let Some((mut command, sent)) = command.take() else {
+                            return;
+                        };
+                        drop(commands);
+                        let (status, body) = response.split_once(' ').unwrap_or((response, ""));
+                        command.duration = sent.elapsed();
+                        command.status = status.parse().unwrap_or(0);
+                        if !(200..400).contains(&command.status) {
+                            command.error = #struct_name_ident::__json_string_field(body, "error");
+                        }
+                        self.finish(command);
+                    }
+                }
+
+                // A command whose span is closed without a response failed to be sent.
+                fn close(&self, id: &span::Id) {
+                    let command = self
+                        .commands
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner)
+                        .remove(&id.into_u64());
+                    if let Some(Some((mut command, sent))) = command {
+                        command.duration = sent.elapsed();
+                        command.error = Some("no response".to_string());
+                        self.finish(command);
+                    }
+                }
+
+                fn finish(&self, command: #command_ident) {
+                    self.log(&command, false);
+                    if self.trace {
+                        #struct_name_ident::__record_command(command);
+                    }
+                }
+
+                fn log(&self, command: &#command_ident, started: bool) {
+                    if self.command_log == "off" {
+                        return;
+                    }
+                    // The events emitted while the recorder handles one are dropped by
+                    // `tracing`, unless the subscriber of the user is made the default.
+                    #tracing::dispatcher::with_default(&self.inner, || {
+                        #struct_name_ident::__log_command(command, started, self.command_log == "file");
+                    });
+                }
+            }
+
+            // `current_span` is left unknown, as `tracing` doesn't export its type to
+            // forward it.
+            impl #tracing::Subscriber for CommandRecorder {
+                fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+                    let interest = self.inner.register_callsite(metadata);
+                    if metadata.target() == TARGET {
+                        return Interest::always();
+                    }
+                    interest
+                }
+
+                fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+                    metadata.target() == TARGET || self.inner.enabled(metadata)
+                }
+
+                fn new_span(&self, attributes: &span::Attributes<'_>) -> span::Id {
+                    let metadata = attributes.metadata();
+                    let id = if metadata.target() != TARGET || self.inner.enabled(metadata) {
+                        self.inner.new_span(attributes)
+                    } else {
+                        span::Id::from_u64(OWN_SPAN | self.next_id.fetch_add(1, Ordering::Relaxed))
+                    };
+                    if metadata.target() == TARGET && metadata.name() == "run_webdriver_cmd" {
+                        self.commands
+                            .lock()
+                            .unwrap_or_else(std::sync::PoisonError::into_inner)
+                            .insert(id.into_u64(), None);
+                    }
+                    id
+                }
+
+                fn record(&self, span: &span::Id, values: &span::Record<'_>) {
+                    if span.into_u64() & OWN_SPAN == 0 {
+                        self.inner.record(span, values);
+                    }
+                }
+
+                fn record_follows_from(&self, span: &span::Id, follows: &span::Id) {
+                    if (span.into_u64() | follows.into_u64()) & OWN_SPAN == 0 {
+                        self.inner.record_follows_from(span, follows);
+                    }
+                }
+
+                fn event(&self, event: &Event<'_>) {
+                    if event.metadata().target() == TARGET {
+                        self.record_event(event);
+                        if !self.inner.enabled(event.metadata()) {
+                            return;
+                        }
+                    }
+                    self.inner.event(event);
+                }
+
+                fn enter(&self, span: &span::Id) {
+                    ENTERED.with(|entered| entered.borrow_mut().push(span.into_u64()));
+                    if span.into_u64() & OWN_SPAN == 0 {
+                        self.inner.enter(span);
+                    }
+                }
+
+                fn exit(&self, span: &span::Id) {
+                    ENTERED.with(|entered| {
+                        let mut entered = entered.borrow_mut();
+                        if let Some(index) = entered.iter().rposition(|id| *id == span.into_u64()) {
+                            entered.remove(index);
+                        }
+                    });
+                    if span.into_u64() & OWN_SPAN == 0 {
+                        self.inner.exit(span);
+                    }
+                }
+
+                fn clone_span(&self, span: &span::Id) -> span::Id {
+                    if span.into_u64() & OWN_SPAN == 0 {
+                        self.inner.clone_span(span)
+                    } else {
+                        span.clone()
+                    }
+                }
+
+                fn try_close(&self, span: span::Id) -> bool {
+                    if span.into_u64() & OWN_SPAN != 0 {
+                        self.close(&span);
+                        return true;
+                    }
+                    let closed = self.inner.try_close(span.clone());
+                    if closed {
+                        self.close(&span);
+                    }
+                    closed
+                }
+            }
+
+            let trace = Self::__discover_trace().is_ok_and(|trace| trace != "off");
+            let command_log = Self::__discover_command_log().unwrap_or_else(|_| "off".to_string());
+            if !trace && command_log == "off" {
+                return;
+            }
+            RECORDER.with(|recorder| {
+                let mut recorder = recorder.borrow_mut();
+                if recorder.is_none() {
+                    let dispatch = #tracing::Dispatch::new(CommandRecorder {
+                        inner: #tracing::dispatcher::get_default(#tracing::Dispatch::clone),
+                        commands: std::sync::Mutex::new(std::collections::BTreeMap::new()),
+                        next_id: AtomicU64::new(1),
+                        trace,
+                        command_log,
+                    });
+                    *recorder = Some(#tracing::dispatcher::set_default(&dispatch));
+                }
+            });
+        }
+
+        // Only the commands of the traced scenarios are kept, so they don't pile up when
+        // only the command log is enabled.
+        fn __record_command(command: #command_ident) {
+            let traced = Self::__trace_sessions()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .values()
+                .any(|(session_id, _)| *session_id == command.session_id);
+            if traced {
+                Self::__recorded_commands()
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .push(command);
+            }
+        }
+    }
+}
+
+pub(crate) fn build_trace_fns(
+    thirtyfour: &syn::Path,
+    tracing: Option<&syn::Path>,
+    struct_name_ident: &TokenTree,
+    command_ident: &Ident,
+    report_ident: &Ident,
+) -> TokenStream {
+    let scenario_ident = format_ident!("{}Scenario", report_ident);
+    let step_ident = format_ident!("{}Step", report_ident);
+    let record_commands = build_record_commands(tracing, struct_name_ident, command_ident);
+
+    quote! {
+        #[doc = "Get when the traces of the scenarios are saved."]
+        #[doc = ""]
+        #[doc = "It's defined by the `TRACE` environment variable, one of `\"off\"` (default),"]
+        #[doc = "`\"on-failure\"` or `\"always\"`. When enabled, the runner of the world takes a"]
+        #[doc = "screenshot and the URL of the page after each step, with the WebDriver commands sent"]
+        #[doc = "by `thirtyfour` when the `tracing` argument of the macro is passed. The trace of the"]
+        #[doc = "scenario is saved as `ARTIFACTS_DIR/<feature>/<scenario>/trace.zip`, with an"]
+        #[doc = "`index.html` viewer to scrub through the steps with their screenshots and commands."]
+        #[must_use]
+        pub fn trace(&self) -> &str {
+            &self.trace
+        }
+
+        #record_commands
+
+        // `Self` can't be used in the type of a static, so the world is named.
+        fn __recorded_commands() -> &'static std::sync::Mutex<Vec<#command_ident>> {
+            static RECORDED_COMMANDS: std::sync::Mutex<Vec<#command_ident>> = std::sync::Mutex::new(vec![]);
+            &RECORDED_COMMANDS
+        }
+
+        fn __take_commands(session_id: &str) -> Vec<#command_ident> {
+            let mut recorded = Self::__recorded_commands()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            let (taken, kept) = std::mem::take(&mut *recorded)
+                .into_iter()
+                .partition(|command| command.session_id == session_id);
+            *recorded = kept;
+            taken
+        }
+
+        // Sessions of the traced scenarios by their artifacts directory. The handles are
+        // weak, so the sessions are still closed when the worlds are dropped.
+        fn __trace_sessions() -> &'static std::sync::Mutex<
+            std::collections::BTreeMap<
+                std::path::PathBuf,
+                (String, std::sync::Weak<#thirtyfour::session::handle::SessionHandle>),
+            >,
+        > {
+            static TRACE_SESSIONS: std::sync::Mutex<
+                std::collections::BTreeMap<
+                    std::path::PathBuf,
+                    (String, std::sync::Weak<#thirtyfour::session::handle::SessionHandle>),
+                >,
+            > = std::sync::Mutex::new(std::collections::BTreeMap::new());
+            &TRACE_SESSIONS
+        }
+
+        fn __start_trace(&self, dir: &std::path::Path) {
+            if self.trace != "off" {
+                Self::__trace_sessions()
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .insert(
+                        dir.to_path_buf(),
+                        (
+                            self.driver().session_id().to_string(),
+                            std::sync::Arc::downgrade(&self.driver().handle),
+                        ),
+                    );
+            }
+        }
+
+        // Commands, URL and screenshot of the last finished step of the traced scenarios.
+        fn __trace_snapshots() -> &'static std::sync::Mutex<
+            std::collections::BTreeMap<std::path::PathBuf, (Vec<#command_ident>, Option<String>, Option<Vec<u8>>)>,
+        > {
+            static TRACE_SNAPSHOTS: std::sync::Mutex<
+                std::collections::BTreeMap<std::path::PathBuf, (Vec<#command_ident>, Option<String>, Option<Vec<u8>>)>,
+            > = std::sync::Mutex::new(std::collections::BTreeMap::new());
+            &TRACE_SNAPSHOTS
+        }
+
+        // Take the URL and a screenshot of the page when a step of a traced scenario is
+        // finished. Called by the writer of the world before the report records the step.
+        async fn __snapshot_trace_step(dir: &std::path::Path) {
+            let session = Self::__trace_sessions()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .get(dir)
+                .cloned();
+            let Some((session_id, handle)) = session else {
+                return;
+            };
+            let commands = Self::__take_commands(&session_id);
+            let (mut url, mut screenshot) = (None, None);
+            if let Some(handle) = handle.upgrade() {
+                url = handle.current_url().await.ok().map(|url| url.to_string());
+                screenshot = handle.screenshot_as_png().await.ok();
+                // The commands of the snapshot itself are not part of the step.
+                Self::__take_commands(&session_id);
+            }
+            Self::__trace_snapshots()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .insert(dir.to_path_buf(), (commands, url, screenshot));
+        }
+
+        // Steps of the traces of the running scenarios as JSON, with their screenshots.
+        fn __traces() -> &'static std::sync::Mutex<
+            std::collections::BTreeMap<std::path::PathBuf, Vec<(String, Option<Vec<u8>>)>>,
+        > {
+            static TRACES: std::sync::Mutex<
+                std::collections::BTreeMap<std::path::PathBuf, Vec<(String, Option<Vec<u8>>)>>,
+            > = std::sync::Mutex::new(std::collections::BTreeMap::new());
+            &TRACES
+        }
+
+        // Record a finished step in the trace of its scenario, with the snapshot taken
+        // by the writer.
+        fn __trace_step(dir: &std::path::Path, step: &#step_ident) {
+            let traced = Self::__trace_sessions()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .contains_key(dir);
+            if !traced {
+                return;
+            }
+            let (commands, url, screenshot) = Self::__trace_snapshots()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .remove(dir)
+                .unwrap_or_default();
+            let json = Self::__json_string;
+            let millis = |time: std::time::SystemTime| {
+                time.duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs_f64() * 1000.0
+            };
+            let commands = commands
+                .iter()
+                .map(|command| {
+                    format!(
+                        "{{\"method\": {}, \"endpoint\": {}, \"payload\": {}, \"started\": {:.1}, \
+                        \"duration\": {:.1}, \"status\": {}, \"error\": {}}}",
+                        json(&command.method),
+                        json(&command.endpoint),
+                        json(&command.payload),
+                        millis(command.started),
+                        command.duration.as_secs_f64() * 1000.0,
+                        command.status,
+                        command.error.as_deref().map_or("null".to_string(), json),
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            let mut traces = Self::__traces().lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+            let steps = traces.entry(dir.to_path_buf()).or_default();
+            let step_json = format!(
+                "{{\"keyword\": {}, \"name\": {}, \"status\": \"{}\", \"error\": {}, \"started\": {:.1}, \
+                \"duration\": {:.1}, \"url\": {}, \"screenshot\": {}, \"commands\": [{commands}]}}",
+                json(&step.keyword),
+                json(&step.name),
+                step.status,
+                step.error.as_deref().map_or("null".to_string(), json),
+                millis(step.started),
+                step.duration.as_secs_f64() * 1000.0,
+                url.as_deref().map_or("null".to_string(), json),
+                if screenshot.is_some() {
+                    format!("\"step-{:03}.png\"", steps.len() + 1)
+                } else {
+                    "null".to_string()
+                },
+            );
+            steps.push((step_json, screenshot));
+        }
+
+        // Save the trace of a finished scenario when the policy requires it, and forget
+        // its session.
+        fn __save_trace(dir: &std::path::Path, feature: &str, scenario: &#scenario_ident) {
+            let session = Self::__trace_sessions()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .remove(dir);
+            Self::__trace_snapshots()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .remove(dir);
+            let steps = Self::__traces()
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .remove(dir);
+            let (Some((session_id, _)), Some(steps)) = (session, steps) else {
+                return;
+            };
+            Self::__take_commands(&session_id);
+            let status = scenario.status();
+            if !Self::__discover_trace().is_ok_and(|trace| trace == "always") && status != "failed" {
+                return;
+            }
+
+            let trace = format!(
+                "{{\"feature\": {}, \"scenario\": {}, \"status\": \"{status}\", \"steps\": [{}]}}",
+                Self::__json_string(feature),
+                Self::__json_string(&scenario.name),
+                steps.iter().map(|(step, _)| step.as_str()).collect::<Vec<_>>().join(", "),
+            );
+            let mut files = vec![(
+                "index.html".to_string(),
+                #VIEWER.replace("/*TRACE*/", &trace.replace("</", "<\\/")).into_bytes(),
+            )];
+            for (index, (_, screenshot)) in steps.into_iter().enumerate() {
+                if let Some(screenshot) = screenshot {
+                    files.push((format!("step-{:03}.png", index + 1), screenshot));
+                }
+            }
+            if let Err(err) = std::fs::create_dir_all(dir) {
+                Self::__warn(&format!("Failed to create the artifacts directory {}: {err}", dir.display()));
+                return;
+            }
+            Self::__write_artifact(&dir.join("trace.zip"), &Self::__zip(&files));
+        }
+
+        // Zip archive with the files stored without compression.
+        fn __zip(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+            let mut archive = vec![];
+            let mut directory = vec![];
+            for (name, content) in files {
+                let offset = u32::try_from(archive.len()).unwrap_or(u32::MAX);
+                let size = u32::try_from(content.len()).unwrap_or(u32::MAX);
+                let name_length = u16::try_from(name.len()).unwrap_or(u16::MAX);
+                // Version 2.0, UTF-8 names, stored, 1980-01-01 00:00.
+                let mut header = vec![];
+                header.extend_from_slice(&20u16.to_le_bytes());
+                header.extend_from_slice(&0x0800u16.to_le_bytes());
+                header.extend_from_slice(&0u16.to_le_bytes());
+                header.extend_from_slice(&0u16.to_le_bytes());
+                header.extend_from_slice(&0x21u16.to_le_bytes());
+                header.extend_from_slice(&Self::__crc32(content).to_le_bytes());
+                header.extend_from_slice(&size.to_le_bytes());
+                header.extend_from_slice(&size.to_le_bytes());
+                header.extend_from_slice(&name_length.to_le_bytes());
+                header.extend_from_slice(&0u16.to_le_bytes());
+
+                archive.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+                archive.extend_from_slice(&header);
+                archive.extend_from_slice(name.as_bytes());
+                archive.extend_from_slice(content);
+
+                directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+                directory.extend_from_slice(&20u16.to_le_bytes());
+                directory.extend_from_slice(&header);
+                // Comment length, disk number, internal and external attributes.
+                directory.extend_from_slice(&[0; 10]);
+                directory.extend_from_slice(&offset.to_le_bytes());
+                directory.extend_from_slice(name.as_bytes());
+            }
+            let entries = u16::try_from(files.len()).unwrap_or(u16::MAX);
+            let directory_offset = u32::try_from(archive.len()).unwrap_or(u32::MAX);
+            let directory_size = u32::try_from(directory.len()).unwrap_or(u32::MAX);
+            archive.extend_from_slice(&directory);
+            archive.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+            archive.extend_from_slice(&[0; 4]);
+            archive.extend_from_slice(&entries.to_le_bytes());
+            archive.extend_from_slice(&entries.to_le_bytes());
+            archive.extend_from_slice(&directory_size.to_le_bytes());
+            archive.extend_from_slice(&directory_offset.to_le_bytes());
+            archive.extend_from_slice(&0u16.to_le_bytes());
+            archive
+        }
+
+        fn __discover_trace() -> Result<String, String> {
+            let trace = Self::__config_value("TRACE")
+                .map_or("off".to_string(), |value| value.trim().to_ascii_lowercase());
+            if !matches!(trace.as_str(), "off" | "on-failure" | "always") {
+                return Err(format!(
+                    "Invalid TRACE environment variable value \"{trace}\". \
+                    Expected one of: \"off\", \"on-failure\" or \"always\"."
+                ));
+            }
+            Ok(trace)
+        }
+    }
+}
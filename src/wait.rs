@@ -0,0 +1,324 @@
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use quote::quote;
+
+/// Pending `fetch` and `XMLHttpRequest` requests of the page, tracked by wrappers
+/// installed the first time the condition is checked, and the time when the
+/// last one finished.
+const REQUESTS_SCRIPT: &str = "(() => { \
+    if (window.__worlderRequests === undefined) { \
+        const requests = window.__worlderRequests = { pending: 0, last: performance.now() }; \
+        const finish = () => { requests.pending -= 1; requests.last = performance.now(); }; \
+        const fetch = window.fetch; \
+        window.fetch = function (...args) { \
+            requests.pending += 1; \
+            const response = fetch.apply(this, args); \
+            response.then(finish, finish); \
+            return response; \
+        }; \
+        const send = XMLHttpRequest.prototype.send; \
+        XMLHttpRequest.prototype.send = function (...args) { \
+            requests.pending += 1; \
+            this.addEventListener('loadend', finish); \
+            return send.apply(this, args); \
+        }; \
+    } \
+    return window.__worlderRequests; \
+})()";
+
+/// WASM resources of the page fetched, which only happens once the module is
+/// requested by the bundle.
+const WASM_SCRIPT: &str = "performance.getEntriesByType('resource') \
+    .some((entry) => entry.name.split(/[?#]/)[0].endsWith('.wasm'))";
+
+/// Trunk exposes the bindings of the WASM module once it has been instantiated.
+const TRUNK_HYDRATION_SCRIPT: &str =
+    "document.readyState === 'complete' && window.wasmBindings !== undefined";
+
+/// cargo-leptos serves the bindings and the module of the application from the
+/// `pkg` directory of the site, and the hydration script fetches both of them.
+const LEPTOS_SCRIPT: &str = "(() => { \
+    const paths = performance.getEntriesByType('resource') \
+        .map((entry) => new URL(entry.name).pathname) \
+        .filter((path) => path.includes('/pkg/')); \
+    return paths.some((path) => path.endsWith('.js')) \
+        && paths.some((path) => path.endsWith('.wasm')); \
+})()";
+
+/// Yew removes the `<!--<[Component]>-->` comments rendered around components on
+/// the server while hydrating them.
+const YEW_SCRIPT: &str = "(() => { \
+    const walker = document.createTreeWalker(document.body, NodeFilter.SHOW_COMMENT); \
+    while (walker.nextNode()) { \
+        if (walker.currentNode.data.startsWith('<[')) { return false; } \
+    } \
+    return true; \
+})()";
+
+/// Dioxus renders the application inside the element with id `main`, which is
+/// empty in the page served to client-side rendered applications.
+const DIOXUS_SCRIPT: &str = "document.getElementById('main')?.hasChildNodes() === true";
+
+/// Presets accepted by the `Hydrated` condition.
+pub(crate) const HYDRATION_PRESETS: [&str; 5] = ["wasm", "trunk", "leptos", "yew", "dioxus"];
+
+pub(crate) fn build_readiness_enum(
+    vis_ident: &TokenTree,
+    struct_name_ident: &TokenTree,
+    readiness_ident: &Ident,
+) -> TokenStream {
+    quote! {
+        #[doc = "Condition to consider a page ready after navigating to it."]
+        #[derive(Debug, Clone, PartialEq)]
+        #vis_ident enum #readiness_ident {
+            #[doc = "The `document.readyState` of the page is `complete`."]
+            DocumentComplete,
+            #[doc = "The page is complete, it has no `fetch` nor `XMLHttpRequest` requests in flight"]
+            #[doc = "and no resources have been loaded during the given time."]
+            #[doc = ""]
+            #[doc = "The requests are tracked from the first time the condition is checked, so those"]
+            #[doc = "started before are only noticed once they finish."]
+            NetworkIdle(std::time::Duration),
+            #[doc = "An element matching the given CSS selector is present in the page."]
+            Selector(String),
+            #[doc = "The given JavaScript expression evaluates to a truthy value."]
+            Script(String),
+            #[doc = "The WASM module of the page has been instantiated and hydrated the page."]
+            #[doc = ""]
+            #[doc = "Accepts a preset or a custom JavaScript expression:"]
+            #[doc = ""]
+            #[doc = "- `\"wasm\"`: the page has fetched its `.wasm` resources and the network has"]
+            #[doc = "  been idle for 100 milliseconds, as defined by `NetworkIdle`. It works for any"]
+            #[doc = "  framework, but it's a heuristic: the page may still be hydrating when a large"]
+            #[doc = "  module is instantiated without requests."]
+            #[doc = "- `\"trunk\"`: the page is complete and the bindings of the WASM module have"]
+            #[doc = "  been exposed by [Trunk] in `window.wasmBindings` after instantiating it."]
+            #[doc = "- `\"leptos\"`: the `\"wasm\"` condition, and the bindings and the module of the"]
+            #[doc = "  application have been fetched from the `pkg` directory served by cargo-leptos."]
+            #[doc = "- `\"yew\"`: the `\"wasm\"` condition, and no `<!--<[Component]>-->` comments"]
+            #[doc = "  rendered by the server remain in the body, as Yew removes them while"]
+            #[doc = "  hydrating each component."]
+            #[doc = "- `\"dioxus\"`: the `\"wasm\"` condition, and the root element of Dioxus, with id"]
+            #[doc = "  `main`, has been rendered."]
+            #[doc = ""]
+            #[doc = "No framework exposes when it has finished hydrating, so for an exact detection"]
+            #[doc = "set a marker from the application once it has hydrated, like in an effect of"]
+            #[doc = "its root component, and pass an expression checking it, like"]
+            #[doc = "`\"window.hydrated === true\"`."]
+            #[doc = ""]
+            #[doc = "[Trunk]: https://trunkrs.dev"]
+            Hydrated(String),
+        }
+
+        impl #readiness_ident {
+            #[doc = "Get the JavaScript expression that evaluates to `true` when the condition is met."]
+            #[must_use]
+            pub fn to_script(&self) -> String {
+                match self {
+                    Self::DocumentComplete => "document.readyState === 'complete'".to_string(),
+                    Self::NetworkIdle(idle) => format!(
+                        "document.readyState === 'complete' && (() => {{ \
+                            const requests = {}; \
+                            const last = Math.max(requests.last, ...performance \
+                                .getEntriesByType('resource').map((entry) => entry.responseEnd)); \
+                            return requests.pending === 0 && performance.now() - last >= {}; \
+                        }})()",
+                        #REQUESTS_SCRIPT,
+                        idle.as_millis(),
+                    ),
+                    Self::Selector(selector) => format!(
+                        "document.querySelector({}) !== null",
+                        #struct_name_ident::__js_string(selector),
+                    ),
+                    Self::Script(script) => script.clone(),
+                    Self::Hydrated(hydration) => match hydration.as_str() {
+                        "wasm" => format!(
+                            "{} && {}",
+                            #WASM_SCRIPT,
+                            Self::NetworkIdle(std::time::Duration::from_millis(100)).to_script(),
+                        ),
+                        "trunk" => #TRUNK_HYDRATION_SCRIPT.to_string(),
+                        "leptos" => format!(
+                            "{} && {}",
+                            Self::Hydrated("wasm".to_string()).to_script(),
+                            #LEPTOS_SCRIPT,
+                        ),
+                        "yew" => format!(
+                            "{} && {}",
+                            Self::Hydrated("wasm".to_string()).to_script(),
+                            #YEW_SCRIPT,
+                        ),
+                        "dioxus" => format!(
+                            "{} && {}",
+                            Self::Hydrated("wasm".to_string()).to_script(),
+                            #DIOXUS_SCRIPT,
+                        ),
+                        script => script.to_string(),
+                    },
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn build_wait_fns(
+    thirtyfour: &syn::Path,
+    readiness_ident: &Ident,
+    hydration: Option<&syn::LitStr>,
+) -> TokenStream {
+    let hydration_option =
+        hydration.map_or_else(|| quote!(None), |hydration| quote!(Some(#hydration)));
+    let wait_for_hydration = hydration.map(|hydration| {
+        quote! {
+            self.wait_until_ready(
+                #readiness_ident::Hydrated(#hydration.to_string()),
+                self.ready_timeout,
+            )
+            .await?;
+        }
+    });
+
+    quote! {
+        #[doc = "Get the timeout of the world waiting for pages to be ready."]
+        #[doc = ""]
+        #[doc = "It's defined by the `READY_TIMEOUT` environment variable, which defaults to `10s`."]
+        #[must_use]
+        pub fn ready_timeout(&self) -> std::time::Duration {
+            self.ready_timeout
+        }
+
+        #[doc = "Navigate to the given path inside the host and wait until the page is ready."]
+        #[doc = ""]
+        #[doc = "Waits up to [`ready_timeout`](Self::ready_timeout) for the condition to be met."]
+        #[doc = ""]
+        #[doc = "# Errors"]
+        #[doc = ""]
+        #[doc = "If the condition is not met before the timeout, returns a `Timeout` error."]
+        pub async fn goto_path_and_wait(
+            &self,
+            path: &str,
+            readiness: #readiness_ident,
+        ) -> Result<&Self, #thirtyfour::error::WebDriverError> {
+            self.goto_path_and_wait_timeout(path, readiness, self.ready_timeout).await
+        }
+
+        #[doc = "Navigate to the given path inside the host and wait until the page is ready,"]
+        #[doc = "up to the given timeout."]
+        #[doc = ""]
+        #[doc = "# Errors"]
+        #[doc = ""]
+        #[doc = "If the condition is not met before the timeout, returns a `Timeout` error."]
+        pub async fn goto_path_and_wait_timeout(
+            &self,
+            path: &str,
+            readiness: #readiness_ident,
+            timeout: std::time::Duration,
+        ) -> Result<&Self, #thirtyfour::error::WebDriverError> {
+            self.goto_path(path).await?;
+            self.wait_until_ready(readiness, timeout).await
+        }
+
+        #[doc = "Wait until the current page is ready, up to the given timeout."]
+        #[doc = ""]
+        #[doc = "# Errors"]
+        #[doc = ""]
+        #[doc = "If the condition is not met before the timeout, returns a `Timeout` error."]
+        pub async fn wait_until_ready(
+            &self,
+            readiness: #readiness_ident,
+            timeout: std::time::Duration,
+        ) -> Result<&Self, #thirtyfour::error::WebDriverError> {
+            let script = format!("return Boolean({});", readiness.to_script());
+            let deadline = std::time::Instant::now() + timeout;
+            loop {
+                let ready = self
+                    .driver()
+                    .execute(script.as_str(), vec![])
+                    .await?
+                    .convert::<bool>()?;
+                if ready {
+                    return Ok(self);
+                }
+                if std::time::Instant::now() >= deadline {
+                    return Err(#thirtyfour::error::WebDriverError::Timeout(format!(
+                        "Timed out after {timeout:?} waiting for the page at {} to be ready \
+                        with condition {readiness:?}",
+                        self.driver().current_url().await?,
+                    )));
+                }
+                #thirtyfour::support::sleep(std::time::Duration::from_millis(100)).await;
+            }
+        }
+
+        #[doc = "Wait until the WASM module of the current page has hydrated it."]
+        #[doc = ""]
+        #[doc = "Uses the `hydration` argument of the `#[worlder]` macro or, if not defined, the"]
+        #[doc = "`\"wasm\"` preset. See the `Hydrated` condition for the available presets."]
+        #[doc = "Waits up to [`ready_timeout`](Self::ready_timeout)."]
+        #[doc = ""]
+        #[doc = "# Errors"]
+        #[doc = ""]
+        #[doc = "If the page is not hydrated before the timeout, returns a `Timeout` error."]
+        pub async fn wait_for_hydration(&self) -> Result<&Self, #thirtyfour::error::WebDriverError> {
+            let hydration: Option<&str> = #hydration_option;
+            self.wait_until_ready(
+                #readiness_ident::Hydrated(hydration.unwrap_or("wasm").to_string()),
+                self.ready_timeout,
+            )
+            .await
+        }
+
+        async fn __after_navigation(&self) -> Result<(), #thirtyfour::error::WebDriverError> {
+            self.__install_console_listener().await;
+            #wait_for_hydration
+            Ok(())
+        }
+
+        fn __js_string(value: &str) -> String {
+            let mut escaped = String::with_capacity(value.len() + 2);
+            escaped.push('"');
+            for c in value.chars() {
+                match c {
+                    '"' => escaped.push_str("\\\""),
+                    '\\' => escaped.push_str("\\\\"),
+                    '\n' => escaped.push_str("\\n"),
+                    '\r' => escaped.push_str("\\r"),
+                    '\u{2028}' => escaped.push_str("\\u2028"),
+                    '\u{2029}' => escaped.push_str("\\u2029"),
+                    c => escaped.push(c),
+                }
+            }
+            escaped.push('"');
+            escaped
+        }
+
+        fn __discover_ready_timeout() -> Result<std::time::Duration, String> {
+            Self::__config_value("READY_TIMEOUT").map_or(
+                Ok(std::time::Duration::from_secs(10)),
+                |value| Self::__parse_duration("READY_TIMEOUT", &value),
+            )
+        }
+
+        fn __parse_duration(name: &str, value: &str) -> Result<std::time::Duration, String> {
+            let value = value.trim();
+            let invalid = || {
+                format!(
+                    "Invalid {name} environment variable value \"{value}\". \
+                    Expected a duration like \"500ms\", \"10s\" or \"2m\"."
+                )
+            };
+            let (number, unit) = value
+                .find(|c: char| !c.is_ascii_digit())
+                .map_or((value, ""), |index| value.split_at(index));
+            let number = number.parse::<u64>().map_err(|_| invalid())?;
+            match unit.trim() {
+                "" | "ms" => Ok(std::time::Duration::from_millis(number)),
+                "s" => Ok(std::time::Duration::from_secs(number)),
+                "m" => number
+                    .checked_mul(60)
+                    .map(std::time::Duration::from_secs)
+                    .ok_or_else(invalid),
+                _ => Err(invalid()),
+            }
+        }
+    }
+}